@@ -1,6 +1,10 @@
 use futures::future::BoxFuture;
+use serde_json::Value;
 
-use super::{modules::content_types::ContentFile, *};
+use super::{
+    modules::content_types::{AssignDates, ContentFile},
+    *,
+};
 
 #[derive(Debug, Deserialize)]
 pub(super) struct ModAssignGetSubmissionStatus {
@@ -9,20 +13,24 @@ pub(super) struct ModAssignGetSubmissionStatus {
     assignmentdata: Option<AssignmentData>,
 }
 impl Download for ModAssignGetSubmissionStatus {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         tracing::trace!("\n{:?}", self);
 
         // Use a lifetime dependent BoxFuture instead of 'static.
         let mut tasks: Vec<BoxFuture<Result<()>>> = Vec::new();
 
         if let Some(lastattempt) = &self.lastattempt {
-            tasks.push(Box::pin(lastattempt.download(config.clone(), path)));
+            tasks.push(Box::pin(lastattempt.download(config.clone(), scope, path)));
         }
         if let Some(feedback) = &self.feedback {
-            tasks.push(Box::pin(feedback.download(config.clone(), path)));
+            tasks.push(Box::pin(feedback.download(config.clone(), scope, path)));
         }
         if let Some(assignmentdata) = &self.assignmentdata {
-            tasks.push(Box::pin(assignmentdata.download(config.clone(), path)));
+            tasks.push(Box::pin(assignmentdata.download(
+                config.clone(),
+                scope,
+                path,
+            )));
         }
 
         // Return an error if one occured
@@ -33,22 +41,138 @@ impl Download for ModAssignGetSubmissionStatus {
     }
 }
 
+impl ModAssignGetSubmissionStatus {
+    /// Saves the full response as `status.json` and a human-readable summary as `status.md`
+    pub(super) async fn save_status(
+        &self,
+        config: &Config,
+        path: &Path,
+        name: &str,
+        dates: &AssignDates,
+        raw: &Value,
+    ) -> Result<()> {
+        config
+            .write_file_contents(
+                &path.join("status.json"),
+                &serde_json::to_string_pretty(raw)?,
+            )
+            .await?;
+        config
+            .write_file_contents(&path.join("status.md"), &self.status_markdown(name, dates))
+            .await?;
+        Ok(())
+    }
+
+    /// One of "graded", "submitted" or "not submitted"
+    pub(super) fn short_status(&self) -> &'static str {
+        let lastattempt = self.lastattempt.as_ref();
+        let graded = lastattempt.and_then(|lastattempt| lastattempt.gradingstatus.as_deref())
+            == Some("graded");
+        let submitted = lastattempt
+            .and_then(|lastattempt| {
+                lastattempt
+                    .submission
+                    .as_ref()
+                    .or(lastattempt.teamsubmission.as_ref())
+            })
+            .and_then(|submission| submission.status.as_deref())
+            == Some("submitted");
+
+        if graded {
+            "graded"
+        } else if submitted {
+            "submitted"
+        } else {
+            "not submitted"
+        }
+    }
+
+    fn status_markdown(&self, name: &str, dates: &AssignDates) -> String {
+        let lastattempt = self.lastattempt.as_ref();
+        let submission = lastattempt.and_then(|lastattempt| {
+            lastattempt
+                .submission
+                .as_ref()
+                .or(lastattempt.teamsubmission.as_ref())
+        });
+        let feedback = self.feedback.as_ref();
+        let grade = feedback.and_then(|feedback| feedback.grade.as_ref());
+
+        let rows = [
+            (
+                "Submission status",
+                submission.and_then(|submission| submission.status.clone()),
+            ),
+            (
+                "Last modified",
+                submission
+                    .and_then(|submission| submission.timemodified)
+                    .map(format_timestamp),
+            ),
+            (
+                "Grading status",
+                lastattempt.and_then(|lastattempt| lastattempt.gradingstatus.clone()),
+            ),
+            (
+                "Opened",
+                dates.allowsubmissionsfromdate.map(format_timestamp),
+            ),
+            ("Due date", dates.duedate.map(format_timestamp)),
+            ("Cut-off date", dates.cutoffdate.map(format_timestamp)),
+            (
+                "Extension due date",
+                lastattempt
+                    .and_then(|lastattempt| lastattempt.extensionduedate)
+                    .filter(|timestamp| *timestamp != 0)
+                    .map(format_timestamp),
+            ),
+            (
+                "Grade",
+                feedback.and_then(|feedback| feedback.gradefordisplay.clone()),
+            ),
+            (
+                "Graded on",
+                feedback
+                    .and_then(|feedback| feedback.gradeddate)
+                    .map(format_timestamp),
+            ),
+            (
+                "Grader (user id)",
+                grade
+                    .and_then(|grade| grade.grader)
+                    .filter(|grader| *grader > 0)
+                    .map(|grader| grader.to_string()),
+            ),
+        ];
+
+        let mut markdown = format!("# {}\n\n| | |\n| --- | --- |\n", name);
+        for (key, value) in rows {
+            if let Some(value) = value {
+                markdown.push_str(&format!("| {} | {} |\n", key, value.replace('\n', " ")));
+            }
+        }
+        markdown
+    }
+}
+
 // lastattempt
 #[derive(Deserialize, Debug)]
 pub(super) struct Lastattempt {
     submission: Option<Submission>,
     teamsubmission: Option<Submission>,
+    extensionduedate: Option<u64>,
+    gradingstatus: Option<String>,
 }
 impl Download for Lastattempt {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         let path = &path.join("last_attempt");
         // We usually only have one of the two submission types, therefore additional threads are unnecessary
         let mut res = Vec::new();
         if let Some(submission) = &self.submission {
-            res.push(submission.download(config.clone(), path).await);
+            res.push(submission.download(config.clone(), scope, path).await);
         }
         if let Some(teamsubmission) = &self.teamsubmission {
-            res.push(teamsubmission.download(config.clone(), path).await);
+            res.push(teamsubmission.download(config.clone(), scope, path).await);
         }
         // Return an error if one occured
         for res in res {
@@ -59,15 +183,17 @@ impl Download for Lastattempt {
 }
 #[derive(Deserialize, Debug)]
 pub(super) struct Submission {
+    status: Option<String>,
+    timemodified: Option<u64>,
     plugins: Vec<Plugin>,
 }
 impl Download for Submission {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Create a task for each content
         let tasks = self
             .plugins
             .iter()
-            .map(|r| r.download(config.clone(), &path));
+            .map(|r| r.download(config.clone(), scope, &path));
         // Return an error if one occured
         for res in join_all(tasks).await {
             res.context("Failed Resource")?;
@@ -79,14 +205,19 @@ impl Download for Submission {
 // feedback
 #[derive(Deserialize, Debug)]
 pub(super) struct Feedback {
+    grade: Option<FeedbackGrade>,
+    gradefordisplay: Option<String>,
+    gradeddate: Option<u64>,
     plugins: Option<Vec<Plugin>>,
 }
 impl Download for Feedback {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         if let Some(plugins) = &self.plugins {
             let path = &path.join("feedback");
             // Create a task for each content
-            let tasks = plugins.iter().map(|r| r.download(config.clone(), &path));
+            let tasks = plugins
+                .iter()
+                .map(|r| r.download(config.clone(), scope, &path));
             // Return an error if one occured
             for res in join_all(tasks).await {
                 res.context("Failed Resource")?;
@@ -96,14 +227,19 @@ impl Download for Feedback {
     }
 }
 
+#[derive(Deserialize, Debug)]
+pub(super) struct FeedbackGrade {
+    grader: Option<i64>,
+}
+
 // assignmentdata
 #[derive(Deserialize, Debug)]
 pub(super) struct AssignmentData {
     attachments: Attachments,
 }
 impl Download for AssignmentData {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        self.attachments.download(config, path).await?;
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
+        self.attachments.download(config, scope, path).await?;
         Ok(())
     }
 }
@@ -112,9 +248,12 @@ pub(super) struct Attachments {
     intro: Vec<ContentFile>,
 }
 impl Download for Attachments {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Create a task for each content
-        let tasks = self.intro.iter().map(|r| r.download(config.clone(), &path));
+        let tasks = self
+            .intro
+            .iter()
+            .map(|r| r.download(config.clone(), scope, &path));
         // Return an error if one occured
         for res in join_all(tasks).await {
             res.context("Failed Resource")?;
@@ -135,10 +274,10 @@ pub(super) enum Plugin {
     Unknown,
 }
 impl Download for Plugin {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         match &self {
-            Plugin::File(plugin_file) => plugin_file.download(config, path).await?,
-            Plugin::EditPdf(edit_pdf) => edit_pdf.download(config, path).await?,
+            Plugin::File(plugin_file) => plugin_file.download(config, scope, path).await?,
+            Plugin::EditPdf(edit_pdf) => edit_pdf.download(config, scope, path).await?,
             Plugin::Unknown => {}
         }
         Ok(())
@@ -150,12 +289,12 @@ pub(super) struct PluginFile {
     fileareas: Vec<PluginFileFiles>,
 }
 impl Download for PluginFile {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Create a task for each content
         let tasks = self
             .fileareas
             .iter()
-            .map(|r| r.download(config.clone(), &path));
+            .map(|r| r.download(config.clone(), scope, &path));
         // Return an error if one occured
         for res in join_all(tasks).await {
             res.context("Failed Resource")?;
@@ -168,9 +307,12 @@ pub(super) struct PluginFileFiles {
     files: Vec<ContentFile>,
 }
 impl Download for PluginFileFiles {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Create a task for each content
-        let tasks = self.files.iter().map(|r| r.download(config.clone(), &path));
+        let tasks = self
+            .files
+            .iter()
+            .map(|r| r.download(config.clone(), scope, &path));
         // Return an error if one occured
         for res in join_all(tasks).await {
             res.context("Failed Resource")?;
@@ -184,12 +326,12 @@ pub(super) struct EditPdf {
     fileareas: Vec<EditPdfArea>,
 }
 impl Download for EditPdf {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Create a task for each content
         let tasks = self
             .fileareas
             .iter()
-            .map(|r| r.download(config.clone(), &path));
+            .map(|r| r.download(config.clone(), scope, &path));
         // Return an error if one occured
         for res in join_all(tasks).await {
             res.context("Failed Resource")?;
@@ -206,10 +348,10 @@ pub(super) enum EditPdfArea {
     Unknown,
 }
 impl Download for EditPdfArea {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         match &self {
             EditPdfArea::Download(edit_pdf_area_download) => {
-                edit_pdf_area_download.download(config, path).await?;
+                edit_pdf_area_download.download(config, scope, path).await?;
             }
             EditPdfArea::Unknown => {}
         }
@@ -221,9 +363,12 @@ pub(super) struct EditPdfAreaDownload {
     files: Vec<ContentFile>,
 }
 impl Download for EditPdfAreaDownload {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Create a task for each content
-        let tasks = self.files.iter().map(|r| r.download(config.clone(), &path));
+        let tasks = self
+            .files
+            .iter()
+            .map(|r| r.download(config.clone(), scope, &path));
         // Return an error if one occured
         for res in join_all(tasks).await {
             res.context("Failed Resource")?;
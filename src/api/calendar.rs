@@ -0,0 +1,361 @@
+use chrono::{DateTime, Utc};
+
+use super::*;
+
+// Descriptions taken from generated moodle docs these can be accessed on any moodle instance with administrator rights via: http://example.com/admin/webservice/documentation.php
+#[derive(Debug, Deserialize)]
+/// Get calendar events
+pub(super) struct CoreCalendarGetCalendarEvents {
+    pub events: Vec<CalendarEvent>,
+}
+#[derive(Debug, Deserialize)]
+pub(super) struct CalendarEvent {
+    /// event id
+    pub id: u64,
+    /// event name
+    pub name: String,
+    /// Description
+    pub description: Option<String>,
+    /// course id
+    pub courseid: Option<u64>,
+    /// module name
+    pub modulename: Option<String>,
+    /// Event type
+    pub eventtype: String,
+    /// timestart
+    pub timestart: u64,
+    /// time duration
+    pub timeduration: u64,
+    /// time modified
+    pub timemodified: u64,
+}
+
+#[derive(Debug, Deserialize)]
+/// Returns the courses and assignments for the users capability
+pub(super) struct ModAssignGetAssignments {
+    pub courses: Vec<AssignmentCourse>,
+}
+#[derive(Debug, Deserialize)]
+pub(super) struct AssignmentCourse {
+    /// course id
+    pub id: u64,
+    pub assignments: Vec<Assignment>,
+}
+#[derive(Debug, Deserialize)]
+pub(super) struct Assignment {
+    /// assignment id
+    pub id: u64,
+    /// course module id
+    pub cmid: u64,
+    /// assignment name
+    pub name: String,
+    /// assignment due date
+    pub duedate: u64,
+    /// last time assignment was modified
+    pub timemodified: u64,
+}
+
+#[derive(Debug, Deserialize)]
+/// Returns a list of quizzes in a provided list of courses
+pub(super) struct ModQuizGetQuizzesByCourses {
+    pub quizzes: Vec<QuizInfo>,
+}
+#[derive(Debug, Deserialize)]
+pub(super) struct QuizInfo {
+    /// Foreign key reference to the course this quiz is part of
+    pub course: u64,
+    /// Quiz name
+    pub name: String,
+    /// The time when this quiz closes. (0 = no restriction.)
+    pub timeclose: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Get calendar action events by courses
+pub(super) struct CoreCalendarGetActionEventsByCourses {
+    pub groupedbycourse: Vec<ActionEventsCourse>,
+}
+#[derive(Debug, Deserialize)]
+pub(super) struct ActionEventsCourse {
+    pub courseid: u64,
+    pub events: Vec<ActionEvent>,
+}
+#[derive(Debug, Deserialize)]
+pub(super) struct ActionEvent {
+    pub name: String,
+    pub modulename: Option<String>,
+    pub timesort: u64,
+}
+
+/// An upcoming deadline as listed by `moo-dl deadlines`
+#[derive(Debug)]
+pub struct UpcomingDeadline {
+    pub due: u64,
+    pub course: String,
+    pub module: String,
+    pub name: String,
+    pub status: String,
+}
+
+/// A single entry of the exported calendar
+struct Deadline {
+    uid: String,
+    summary: String,
+    description: String,
+    url: Option<String>,
+    start: u64,
+    duration: u64,
+    modified: u64,
+}
+
+impl Config {
+    async fn save_deadlines_inner(&self, path: &Path) -> Result<()> {
+        let course_ids: Vec<u64> = self.courses.iter().map(|course| course.id).collect();
+        let (events, assignments) = tokio::join!(
+            self.core_calendar_get_calendar_events(&course_ids),
+            self.mod_assign_get_assignments(&course_ids)
+        );
+        let (events, assignments) = (events?, assignments?);
+
+        let host = self.get_moodle_url().host_str().unwrap_or("moodle");
+        let course_name = |course_id: Option<u64>| {
+            self.courses
+                .iter()
+                .find(|course| Some(course.id) == course_id)
+                .map(|course| course.name.as_str())
+        };
+        let summary = |course_id: Option<u64>, name: &str| match course_name(course_id) {
+            Some(course) => format!("{}: {}", course, name),
+            None => name.to_string(),
+        };
+
+        let mut deadlines = Vec::new();
+        for course in assignments.courses {
+            for assignment in course.assignments {
+                // A due date of 0 means the assignment has no due date
+                if assignment.duedate == 0 {
+                    continue;
+                }
+                let mut url = self.get_moodle_url().join("mod/assign/view.php")?;
+                url.query_pairs_mut()
+                    .append_pair("id", &assignment.cmid.to_string());
+                deadlines.push(Deadline {
+                    uid: format!("moo-dl-assign-{}@{}", assignment.id, host),
+                    summary: summary(Some(course.id), &assignment.name),
+                    description: String::new(),
+                    url: Some(url.to_string()),
+                    start: assignment.duedate,
+                    duration: 0,
+                    modified: assignment.timemodified,
+                });
+            }
+        }
+        for event in events.events {
+            // Assignment due dates are already covered by mod_assign_get_assignments
+            if event.modulename.as_deref() == Some("assign") && event.eventtype == "due" {
+                continue;
+            }
+            deadlines.push(Deadline {
+                uid: format!("moo-dl-event-{}@{}", event.id, host),
+                summary: summary(event.courseid, &event.name),
+                description: html_to_text(event.description.as_deref().unwrap_or_default()),
+                url: None,
+                start: event.timestart,
+                duration: event.timeduration,
+                modified: event.timemodified,
+            });
+        }
+        // Keep the file stable between runs
+        deadlines.sort_by(|a, b| (a.start, &a.uid).cmp(&(b.start, &b.uid)));
+
+        let file_path = path.join("deadlines.ics");
+        let mut calendar = to_icalendar(&deadlines, Utc::now().timestamp() as u64);
+        // Only the export time changed: Keep the old file (instead of archiving it every run)
+        if let Ok(previous) = tokio::fs::read_to_string(&file_path).await
+            && without_dtstamp(&previous) == without_dtstamp(&calendar)
+        {
+            calendar = previous;
+        }
+        self.write_file_contents(&file_path, &calendar).await
+    }
+
+    /// Get everything that is due within the next `days` days, sorted by due date
+    ///
+    /// Includes assignments (with their submission status), quizzes and calendar action events
+    pub async fn api_acquire_upcoming_deadlines(&self, days: u64) -> Result<Vec<UpcomingDeadline>> {
+        let now = Utc::now().timestamp() as u64;
+        let until = now + days * 24 * 60 * 60;
+        let in_range = |timestamp: u64| timestamp >= now && timestamp <= until;
+
+        let course_ids: Vec<u64> = self.courses.iter().map(|course| course.id).collect();
+        let (assignments, quizzes, events) = tokio::join!(
+            self.mod_assign_get_assignments(&course_ids),
+            self.mod_quiz_get_quizzes_by_courses(&course_ids),
+            self.core_calendar_get_action_events_by_courses(&course_ids, now, until)
+        );
+        let (assignments, quizzes, events) = (assignments?, quizzes?, events?);
+
+        let course_name = |course_id: u64| {
+            self.courses
+                .iter()
+                .find(|course| course.id == course_id)
+                .map(|course| course.name.clone())
+                .unwrap_or_else(|| course_id.to_string())
+        };
+
+        // Assignments need an additional request for the submission status
+        let assignment_tasks = assignments
+            .courses
+            .iter()
+            .flat_map(|course| {
+                course
+                    .assignments
+                    .iter()
+                    .map(move |assignment| (course.id, assignment))
+            })
+            .filter(|(_, assignment)| in_range(assignment.duedate))
+            .map(|(course_id, assignment)| async move {
+                let status = match self.mod_assign_get_submission_status(assignment.id).await {
+                    Ok((status, _)) => status.short_status().to_string(),
+                    Err(_) => "unknown".to_string(),
+                };
+                UpcomingDeadline {
+                    due: assignment.duedate,
+                    course: course_name(course_id),
+                    module: "assign".to_string(),
+                    name: assignment.name.clone(),
+                    status,
+                }
+            });
+        let mut deadlines = join_all(assignment_tasks).await;
+
+        for quiz in quizzes.quizzes {
+            let due = match quiz.timeclose {
+                Some(timeclose) if in_range(timeclose) => timeclose,
+                _ => continue,
+            };
+            deadlines.push(UpcomingDeadline {
+                due,
+                course: course_name(quiz.course),
+                module: "quiz".to_string(),
+                name: quiz.name,
+                status: "-".to_string(),
+            });
+        }
+
+        for course in events.groupedbycourse {
+            for event in course.events {
+                // Assignments and quizzes are already covered above
+                let module = event.modulename.unwrap_or_else(|| "event".to_string());
+                if module == "assign" || module == "quiz" || !in_range(event.timesort) {
+                    continue;
+                }
+                deadlines.push(UpcomingDeadline {
+                    due: event.timesort,
+                    course: course_name(course.courseid),
+                    module,
+                    name: event.name,
+                    status: "-".to_string(),
+                });
+            }
+        }
+
+        deadlines.sort_by(|a, b| (a.due, &a.course, &a.name).cmp(&(b.due, &b.course, &b.name)));
+        Ok(deadlines)
+    }
+
+    /// Save all deadlines and upcoming events as `deadlines.ics` (if requested by config)
+    pub async fn save_deadlines(&self, path: &Path) {
+        if !self.deadlines {
+            return;
+        }
+        if let Err(e) = self.save_deadlines_inner(path).await {
+            self.register_api_err(e.context("Failed saving deadlines"))
+                .await;
+        }
+    }
+}
+
+/// Create an iCalendar (RFC 5545) file from the given deadlines, exported at `now`
+fn to_icalendar(deadlines: &[Deadline], now: u64) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//moo-dl//deadlines//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:Moodle deadlines".to_string(),
+    ];
+    for deadline in deadlines {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", deadline.uid));
+        lines.push(format!("DTSTAMP:{}", ical_timestamp(now)));
+        if deadline.modified > 0 {
+            lines.push(format!(
+                "LAST-MODIFIED:{}",
+                ical_timestamp(deadline.modified)
+            ));
+        }
+        lines.push(format!("DTSTART:{}", ical_timestamp(deadline.start)));
+        if deadline.duration > 0 {
+            lines.push(format!(
+                "DTEND:{}",
+                ical_timestamp(deadline.start + deadline.duration)
+            ));
+        }
+        lines.push(format!("SUMMARY:{}", ical_escape(&deadline.summary)));
+        if !deadline.description.is_empty() {
+            lines.push(format!(
+                "DESCRIPTION:{}",
+                ical_escape(&deadline.description)
+            ));
+        }
+        if let Some(url) = &deadline.url {
+            lines.push(format!("URL:{}", url));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| ical_fold(line)).collect()
+}
+
+/// The calendar without the export time (DTSTAMP lines are never folded)
+fn without_dtstamp(calendar: &str) -> Vec<&str> {
+    calendar
+        .lines()
+        .filter(|line| !line.starts_with("DTSTAMP:"))
+        .collect()
+}
+
+/// Format a unix timestamp as an utc date-time
+fn ical_timestamp(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Escape a text value
+fn ical_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line to lines of at most 75 octets (terminated by CRLF)
+fn ical_fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_length = 0;
+    for c in line.chars() {
+        if line_length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+        folded.push(c);
+        line_length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
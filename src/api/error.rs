@@ -0,0 +1,165 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use super::*;
+
+/// Error codes, that mean the user is not allowed to access something (instead of something being broken)
+const PERMISSION_ERRORCODES: [&str; 7] = [
+    "accessexception",
+    "nopermissions",
+    "nopermission",
+    "requireloginerror",
+    "coursehidden",
+    "notenrolled",
+    "moduleunavailable",
+];
+
+/// Error payload of the moodle web service (`{"exception": ..., "errorcode": ..., "message": ...}`)
+#[derive(Debug, Deserialize, Clone)]
+pub struct MoodleException {
+    pub exception: String,
+    pub errorcode: String,
+    pub message: String,
+}
+
+/// Entry of the `warnings` list, that many web service functions return
+#[derive(Debug, Deserialize, Clone)]
+pub struct MoodleWarning {
+    pub item: Option<String>,
+    pub itemid: Option<u64>,
+    pub warningcode: String,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WarningsPayload {
+    warnings: Vec<MoodleWarning>,
+}
+
+/// Error of a moodle web service call
+#[derive(Debug)]
+pub enum ApiError {
+    /// Moodle answered with an exception
+    Exception(MoodleException),
+    /// Moodle answered only with warnings, instead of the requested data
+    Warnings {
+        function: String,
+        warnings: Vec<MoodleWarning>,
+    },
+    /// The answer does not have the expected structure
+    Deserialization {
+        function: String,
+        error: serde_json::Error,
+    },
+}
+
+/// Kinds of web service errors, that need special handling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoodleErrorKind {
+    /// The wstoken is unknown, revoked or expired
+    InvalidToken,
+    /// The user is not allowed to access something (e.g. missing capability, hidden course)
+    Permission,
+    Other,
+}
+
+impl ApiError {
+    /// Interpret the answer of a web service function
+    pub fn parse<T>(function: &str, body: &str) -> Result<T, ApiError>
+    where
+        T: for<'a> Deserialize<'a>,
+    {
+        // An exception may deserialize into loosely typed targets (e.g. `Value`)
+        if let Ok(exception) = serde_json::from_str::<MoodleException>(body) {
+            return Err(ApiError::Exception(exception));
+        }
+        serde_json::from_str(body).map_err(|error| {
+            match serde_json::from_str::<WarningsPayload>(body) {
+                Ok(payload) if !payload.warnings.is_empty() => ApiError::Warnings {
+                    function: function.to_string(),
+                    warnings: payload.warnings,
+                },
+                _ => ApiError::Deserialization {
+                    function: function.to_string(),
+                    error,
+                },
+            }
+        })
+    }
+
+    pub fn kind(&self) -> MoodleErrorKind {
+        let is_permission = |code: &str| PERMISSION_ERRORCODES.contains(&code);
+        match self {
+            ApiError::Exception(exception) if exception.errorcode == "invalidtoken" => {
+                MoodleErrorKind::InvalidToken
+            }
+            ApiError::Exception(exception) if is_permission(&exception.errorcode) => {
+                MoodleErrorKind::Permission
+            }
+            ApiError::Warnings { warnings, .. }
+                if warnings
+                    .iter()
+                    .all(|warning| is_permission(&warning.warningcode)) =>
+            {
+                MoodleErrorKind::Permission
+            }
+            _ => MoodleErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Exception(exception) => write!(
+                f,
+                "Moodle error {} ({}): {}",
+                exception.errorcode, exception.exception, exception.message
+            ),
+            ApiError::Warnings { function, warnings } => {
+                let warnings = warnings
+                    .iter()
+                    .map(|warning| match (&warning.item, warning.itemid) {
+                        (Some(item), Some(itemid)) => format!(
+                            "{} {} ({}): {}",
+                            item, itemid, warning.warningcode, warning.message
+                        ),
+                        _ => format!("{}: {}", warning.warningcode, warning.message),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "Moodle warnings for {}: {}", function, warnings)
+            }
+            ApiError::Deserialization { function, error } => {
+                write!(f, "Unexpected answer of {}: {}", function, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Get the web service error of an error chain (if it is one)
+pub fn api_error(error: &anyhow::Error) -> Option<&ApiError> {
+    error.downcast_ref::<ApiError>()
+}
+
+/// Check if an error only means, that the user is not allowed to access something
+pub fn is_permission_error(error: &anyhow::Error) -> bool {
+    api_error(error).is_some_and(|error| error.kind() == MoodleErrorKind::Permission)
+}
+
+impl Config {
+    /// Register a failed operation: Permission errors are skipped, everything else is an error
+    ///
+    /// The message contains the whole error chain (including moodle's errorcode and message)
+    pub async fn register_api_err(&self, error: anyhow::Error) {
+        let message = format!("{:#}", error);
+        if is_permission_error(&error) {
+            self.status_bar.register_skipped_reason(&message).await;
+        } else {
+            self.status_bar.register_err(&message).await;
+        }
+    }
+}
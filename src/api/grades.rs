@@ -0,0 +1,142 @@
+use serde::Serialize;
+
+use super::*;
+
+// Descriptions taken from generated moodle docs these can be accessed on any moodle instance with administrator rights via: http://example.com/admin/webservice/documentation.php
+#[derive(Debug, Deserialize)]
+/// Returns the complete list of grade items for users in a course
+pub(super) struct GradereportUserGetGradeItems {
+    usergrades: Vec<UserGrades>,
+}
+#[derive(Debug, Deserialize)]
+struct UserGrades {
+    gradeitems: Vec<RawGradeItem>,
+}
+#[derive(Debug, Deserialize)]
+struct RawGradeItem {
+    /// Grade item id
+    id: u64,
+    /// Grade item name (unset for course and category totals)
+    itemname: Option<String>,
+    /// Grade item type (e.g. course, category, mod, manual)
+    itemtype: String,
+    graderaw: Option<f64>,
+    gradeformatted: Option<String>,
+    rangeformatted: Option<String>,
+    percentageformatted: Option<String>,
+    feedback: Option<String>,
+}
+
+/// A single grade item, as it gets exported to `grades.json` and `grades.csv`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct GradeItem {
+    pub id: u64,
+    pub name: String,
+    pub grade: String,
+    pub grade_raw: Option<f64>,
+    pub range: String,
+    pub percentage: String,
+    pub feedback: String,
+}
+
+impl GradereportUserGetGradeItems {
+    /// Convert the grade items of the (only) user into plain text grade items
+    pub(super) fn into_grade_items(self) -> Vec<GradeItem> {
+        self.usergrades
+            .into_iter()
+            .flat_map(|user_grades| user_grades.gradeitems)
+            .map(|item| GradeItem {
+                id: item.id,
+                name: match (item.itemname, item.itemtype.as_str()) {
+                    (Some(name), _) if !name.is_empty() => name,
+                    (_, "course") => "Course total".to_string(),
+                    (_, "category") => "Category total".to_string(),
+                    (_, item_type) => item_type.to_string(),
+                },
+                // Moodle returns most grade values as (escaped) html snippets
+                grade: html_to_text(item.gradeformatted.as_deref().unwrap_or_default()),
+                grade_raw: item.graderaw,
+                range: html_to_text(item.rangeformatted.as_deref().unwrap_or_default()),
+                percentage: html_to_text(item.percentageformatted.as_deref().unwrap_or_default()),
+                feedback: html_to_text(item.feedback.as_deref().unwrap_or_default()),
+            })
+            .collect()
+    }
+}
+
+/// Get all graded items, whose grade or feedback changed since the previous run
+pub fn changed_grades<'a>(
+    previous: &'a [GradeItem],
+    current: &'a [GradeItem],
+) -> impl Iterator<Item = (Option<&'a GradeItem>, &'a GradeItem)> {
+    current
+        .iter()
+        // Only consider items, that actually have a grade
+        .filter(|item| item.grade_raw.is_some())
+        .map(|item| (previous.iter().find(|old| old.id == item.id), item))
+        .filter(|(old, item)| match old {
+            Some(old) => old.grade != item.grade || old.feedback != item.feedback,
+            None => true,
+        })
+}
+
+impl GradeItem {
+    /// Describe the change from a previous version of this item (for the log)
+    pub fn change_message(&self, previous: Option<&GradeItem>, path: &Path) -> String {
+        let old_grade = match previous {
+            Some(previous) if !previous.grade.is_empty() => previous.grade.as_str(),
+            _ => "-",
+        };
+        let mut message = format!(
+            "{} \"{}\": {} -> {}",
+            path.to_str().unwrap_or("Unavailable"),
+            self.name,
+            old_grade,
+            self.grade
+        );
+        if !self.feedback.is_empty() {
+            message.push_str(&format!(
+                " (Feedback: {})",
+                self.feedback.replace('\n', " ")
+            ));
+        }
+        message
+    }
+
+    fn csv_record(&self) -> [&str; 5] {
+        [
+            &self.name,
+            &self.grade,
+            &self.range,
+            &self.percentage,
+            &self.feedback,
+        ]
+    }
+}
+
+/// Header of `grades.csv`
+const CSV_HEADER: [&str; 5] = ["item", "grade", "range", "percentage", "feedback"];
+
+/// Create the contents of a `grades.csv`
+pub fn grades_csv(grades: &[GradeItem]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(CSV_HEADER)?;
+    for grade in grades {
+        writer.write_record(grade.csv_record())?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Create the contents of a `grades_overview.csv` (the same as `grades.csv`, prefixed by the course)
+pub fn grades_overview_csv<'a>(
+    courses: impl IntoIterator<Item = (&'a str, &'a [GradeItem])>,
+) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["course"].iter().chain(CSV_HEADER.iter()))?;
+    for (course, grades) in courses {
+        for grade in grades {
+            writer.write_record([course].into_iter().chain(grade.csv_record()))?;
+        }
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
@@ -1,4 +1,12 @@
-use super::{rest::CoreEnrolGetUsersCourses, *};
+use std::collections::HashMap;
+
+use tokio::fs;
+
+use super::{
+    grades::{GradeItem, changed_grades, grades_csv, grades_overview_csv},
+    rest::CoreEnrolGetUsersCourses,
+    *,
+};
 
 impl Config {
     /// Get the user id of the current user
@@ -13,6 +21,63 @@ impl Config {
         Ok(courses)
     }
 
+    /// Get the names of course categories (e.g. the semester)
+    pub async fn api_acquire_category_names(
+        &self,
+        category_ids: &[u64],
+    ) -> Result<HashMap<u64, String>> {
+        let categories = self.core_course_get_categories(category_ids).await?;
+        Ok(categories
+            .into_iter()
+            .map(|category| (category.id, category.name))
+            .collect())
+    }
+
+    /// Save the html summary of a course or section and download the linked youtube videos and sciebo files
+    pub async fn save_html_summary(
+        config: Arc<Config>,
+        scope: &FilterScope,
+        html: &str,
+        path: &Path,
+        file_name: &str,
+    ) -> Result<()> {
+        // Check for youtube vidoes
+        config
+            .queue_youtube_vidoes_extract(scope, html, path.to_owned())
+            .await?;
+        // Check for sciebo links
+        Config::extract_sciebo_download(config.clone(), scope, html, path.to_owned()).await?;
+
+        config
+            .write_file_contents(&path.join(file_name), html)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Save the course summary as `_course.html` (if the course has one)
+    pub async fn save_course_summary(
+        config: Arc<Config>,
+        path: &Path,
+        course_id: u64,
+    ) -> Result<()> {
+        let courses = config
+            .core_course_get_courses_by_field("id", &course_id.to_string())
+            .await?;
+        let summary = courses
+            .courses
+            .into_iter()
+            .next()
+            .and_then(|course| course.summary)
+            .filter(|summary| !summary.trim().is_empty());
+
+        if let Some(summary) = summary {
+            let scope = config.filter_scope.clone();
+            Config::save_html_summary(config, &scope, &summary, path, "_course.html").await?;
+        }
+        Ok(())
+    }
+
     async fn save_grades_table_inner(&self, path: &Path, course_id: u64) -> Result<()> {
         let page_path = &path.join("grades");
         let mut page_url = self.get_moodle_url().join("grade/report/user/index.php")?;
@@ -29,15 +94,80 @@ impl Config {
         Ok(())
     }
 
+    async fn export_grades(&self, path: &Path, course_id: u64) -> Result<Vec<GradeItem>> {
+        let grades = self
+            .gradereport_user_get_grade_items(course_id)
+            .await?
+            .into_grade_items();
+
+        // Compare against the grades of the previous run (if there was one)
+        let grades_path = path.join("grades.json");
+        let previous = fs::read_to_string(&grades_path)
+            .await
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Vec<GradeItem>>(&contents).ok());
+        if let Some(previous) = previous {
+            for (old, new) in changed_grades(&previous, &grades) {
+                self.status_bar
+                    .register_graded(&new.change_message(old, path))
+                    .await;
+            }
+        }
+
+        self.write_file_contents(&grades_path, &serde_json::to_string_pretty(&grades)?)
+            .await?;
+        self.write_file_contents(&path.join("grades.csv"), &grades_csv(&grades)?)
+            .await?;
+
+        Ok(grades)
+    }
+
     /// Save a grade table (if requested by config)
-    pub async fn save_grades_table(&self, path: &Path, course_id: u64) {
-        if self.grades {
-            if let Err(e) = self.save_grades_table_inner(path, course_id).await {
-                let message = e
-                    .context(format!("Failed saving grades {}", course_id))
-                    .to_string();
-                self.status_bar.register_err(&message).await;
+    /// # Returns
+    /// The grades of the course, if they could be exported
+    pub async fn save_grades_table(&self, path: &Path, course_id: u64) -> Option<Vec<GradeItem>> {
+        if !self.grades {
+            return None;
+        }
+
+        // The exported grades don't depend on the (chromium) saved page
+        let (grades, page) = tokio::join!(
+            self.export_grades(path, course_id),
+            self.save_grades_table_inner(path, course_id)
+        );
+        if let Err(e) = page {
+            self.register_api_err(e.context(format!("Failed saving grades {}", course_id)))
+                .await;
+        }
+        match grades {
+            Ok(grades) => Some(grades),
+            Err(e) => {
+                self.register_api_err(e.context(format!("Failed exporting grades {}", course_id)))
+                    .await;
+                None
             }
         }
     }
+
+    async fn save_grades_overview_inner(
+        &self,
+        path: &Path,
+        grades: &[(&str, Vec<GradeItem>)],
+    ) -> Result<()> {
+        let contents = grades_overview_csv(
+            grades
+                .iter()
+                .map(|(course, grades)| (*course, grades.as_slice())),
+        )?;
+        self.write_file_contents(&path.join("grades_overview.csv"), &contents)
+            .await
+    }
+
+    /// Save the grades of all courses into a single `grades_overview.csv`
+    pub async fn save_grades_overview(&self, path: &Path, grades: &[(&str, Vec<GradeItem>)]) {
+        if let Err(e) = self.save_grades_overview_inner(path, grades).await {
+            let message = e.context("Failed saving grades overview").to_string();
+            self.status_bar.register_err(&message).await;
+        }
+    }
 }
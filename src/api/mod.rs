@@ -1,27 +1,38 @@
 // Everthing from here on out should start with api_
 pub mod assign_submission;
+pub mod calendar;
+pub mod error;
+pub mod grades;
 pub mod helpers;
 pub mod modules;
 mod rest;
 
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::Context;
+use chrono::{DateTime, Local};
+use error::{ApiError, is_permission_error};
 use futures::future::join_all;
 use rest::CoreCourseGetContentsElement;
+pub use rest::CoreEnrolGetUsersCourses;
+use select::{document::Document, predicate::Name};
 use serde::Deserialize;
 use tracing::debug;
 
+use crate::config::filter::{FilterItem, FilterScope};
+use crate::config::path_template::PathTemplate;
 use crate::config::sync_config::Config;
 use crate::config::sync_config::Module as ConfigModule;
+use crate::download::{MAX_PATH_COMPONENT_LENGTH, sanitize_path_component};
 use modules::Module;
 
 use crate::Result;
 
 pub trait Download: Debug {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()>;
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()>;
 }
 
 impl Config {
@@ -50,47 +61,183 @@ impl Config {
             .query(query)
             .send()
             .await?;
-        Ok(serde_json::from_str(&response.text().await?)?)
+        let body = response.text().await?;
+        let function = query
+            .iter()
+            .find(|(key, _)| *key == "wsfunction")
+            .map_or("", |(_, value)| value);
+        Ok(ApiError::parse(function, &body)?)
     }
 }
 
 /// Assemble a file path from the `api_filepath`, as provided by the api
 pub fn assemble_path(path: &Path, api_filepath: &str, filename: &str) -> PathBuf {
-    let custom_path = api_filepath.strip_prefix('/').unwrap_or(api_filepath);
+    let mut path = path.to_path_buf();
+    for component in api_filepath.split('/').filter(|c| !c.is_empty()) {
+        path.push(sanitize_path_component(component));
+    }
+    path.join(sanitize_path_component(filename))
+}
+
+/// Rename sections and modules, whose folders would collide on disk
+///
+/// Folders are compared as rendered by the path template (sanitized and case-insensitive, as on
+/// windows and macOS). The first occurrence keeps its name, later ones get the section number or
+/// module id appended.
+pub fn deduplicate_names(elements: &mut [CoreCourseGetContentsElement], template: &PathTemplate) {
+    let key = |path: PathBuf| path.to_string_lossy().to_lowercase();
+
+    let mut sections = HashSet::new();
+    // Modules of different sections share a folder, if the template has no section segments
+    let mut modules = HashSet::new();
+    for element in elements.iter_mut() {
+        let index = element.section;
+        // Renaming only helps, if the name is part of the section folder
+        if template.has_section_name() {
+            element.name = unique_name(&element.name, &index.to_string(), |name| {
+                sections.insert(key(template.section_path(Path::new(""), name, index)))
+            });
+        }
+
+        let section_path = template.section_path(Path::new(""), &element.name, index);
+        for module in element.modules.iter_mut() {
+            // The names vpls and labels actually write to disk
+            let extension = match module {
+                Module::Vpl(_) => ".vpl",
+                Module::Label(_) => ".html",
+                _ => "",
+            };
+            let Some((name, id)) = module.name_and_id_mut() else {
+                continue;
+            };
+            *name = unique_name(name, &id.to_string(), |name| {
+                let name = format!("{}{}", name, extension);
+                modules.insert(key(template.module_path(&section_path, &name)))
+            });
+        }
+    }
+}
+
+/// Returns `name` if `insert` accepts it, otherwise `name (suffix)`
+///
+/// The name is shortened, so that the suffix survives the length limit of path components.
+fn unique_name(name: &str, suffix: &str, mut insert: impl FnMut(&str) -> bool) -> String {
+    if insert(name) {
+        return name.to_string();
+    }
+
+    let suffix = format!(" ({})", suffix);
+    let mut stem = sanitize_path_component(name);
+    let mut end = stem
+        .len()
+        .min(MAX_PATH_COMPONENT_LENGTH.saturating_sub(suffix.len()));
+    loop {
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        stem.truncate(end);
+        let candidate = format!("{}{}", stem.trim_end(), suffix);
+        if insert(&candidate) {
+            return candidate;
+        }
+        if stem.is_empty() {
+            break;
+        }
+        // Template literals may still cut the suffix off, or the shortened name collides as well
+        end = stem.len().saturating_sub(10);
+    }
+    (2..)
+        .map(|counter| format!("{} {}", suffix.trim_start(), counter))
+        .find(|candidate| insert(candidate))
+        .unwrap_or_default()
+}
 
-    path.join(custom_path).join(filename)
+/// Formats a unix timestamp in local time (e.g. 2025-01-31 23:59)
+pub fn format_timestamp(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|date| {
+            date.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Convert a html snippet (as often returned by the api) to plain text
+pub fn html_to_text(html: &str) -> String {
+    Document::from(html)
+        .find(Name("body"))
+        .next()
+        .map(|body| body.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
 }
 
 impl Download for CoreCourseGetContentsElement {
     // Downloads only the elements, that are requested in regards to the config
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Respect course element names
-        let path = path.join(&self.name);
+        let path = config
+            .path_template
+            .section_path(path, &self.name, self.section);
+
+        // Save the section summary (e.g. the weekly plan)
+        let summary = self.summary.as_deref().unwrap_or_default();
+        if !summary.trim().is_empty()
+            && let Err(err) =
+                Config::save_html_summary(config.clone(), scope, summary, &path, "_section.html")
+                    .await
+        {
+            // The modules of the section are still worth downloading
+            config
+                .register_api_err(err.context("Failed section summary"))
+                .await;
+        }
 
         // Create a task for each element
-        let (skip_count, tasks): (u32, Vec<_>) =
-            self.modules
-                .iter()
-                .fold((0, Vec::new()), |(mut skip_count, mut tasks), module| {
-                    if let Some(cfg_module) = config_module_of(module) {
-                        if config.modules.contains(&cfg_module) {
-                            tasks.push(module.download(config.clone(), &path));
-                        } else {
-                            skip_count += 1;
-                        }
-                    };
-
-                    (skip_count, tasks)
-                });
-
-        // Register the correct amount of skipps
-        for _ in 0..skip_count {
-            config.status_bar.register_skipped().await;
+        let path = &path;
+        let mut tasks = Vec::new();
+        for module in &self.modules {
+            let Some(cfg_module) = config_module_of(module) else {
+                continue;
+            };
+            if !config.modules.contains(&cfg_module) {
+                config.status_bar.register_skipped().await;
+                continue;
+            }
+
+            // Check against filters (e.g. to exclude pages or labels)
+            let scope = FilterScope {
+                module: Some(cfg_module),
+                ..scope.clone()
+            };
+            let name = module.name().unwrap_or_default();
+            let module_path = config.path_template.module_path(path, name);
+            let filter_item = FilterItem {
+                path: Some(&module_path),
+                name,
+                ..Default::default()
+            };
+            if config.check_module_filter(&filter_item, &scope).await {
+                continue;
+            }
+            let config = config.clone();
+            tasks.push(async move { module.download(config, &scope, path).await });
         }
 
-        // Return an error if one occured
+        // Return an error if one occured (modules the user can not access are only skipped)
         for res in join_all(tasks).await {
-            res.context("Failed Module")?;
+            if let Err(e) = res {
+                if is_permission_error(&e) {
+                    config
+                        .status_bar
+                        .register_skipped_reason(&format!("{:#}", e))
+                        .await;
+                    continue;
+                }
+                return Err(e.context("Failed Module"));
+            }
         }
         Ok(())
     }
@@ -115,3 +262,63 @@ fn config_module_of(m: &Module) -> Option<ConfigModule> {
         Module::Unknown => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(json: serde_json::Value) -> Vec<CoreCourseGetContentsElement> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn module_names(elements: &[CoreCourseGetContentsElement]) -> Vec<&str> {
+        elements
+            .iter()
+            .flat_map(|element| element.modules.iter().filter_map(|module| module.name()))
+            .collect()
+    }
+
+    #[test]
+    fn deduplicate_across_sections_without_section_folders() {
+        let mut elements = elements(serde_json::json!([
+            {"section": 0, "name": "Intro", "modules": [
+                {"modname": "resource", "id": 1, "name": "Slides"},
+                {"modname": "label", "id": 2, "name": "Slides", "description": ""},
+            ]},
+            {"section": 1, "name": "Intro", "modules": [
+                {"modname": "resource", "id": 3, "name": "slides"},
+                {"modname": "vpl", "id": 4, "name": "Task"},
+                {"modname": "resource", "id": 5, "name": "Task.vpl"},
+            ]},
+        ]));
+        deduplicate_names(&mut elements, &PathTemplate::parse("{module}").unwrap());
+        // Renaming sections does not change any folder
+        assert_eq!(elements[1].name, "Intro");
+        assert_eq!(
+            module_names(&elements),
+            ["Slides", "Slides", "slides (3)", "Task", "Task.vpl (5)"]
+        );
+    }
+
+    #[test]
+    fn deduplicate_long_names() {
+        let long = "a".repeat(300);
+        let mut elements = elements(serde_json::json!([
+            {"section": 0, "name": long, "modules": []},
+            {"section": 1, "name": long, "modules": []},
+            {"section": 2, "name": format!("{} (1)", long), "modules": []},
+        ]));
+        let template = PathTemplate::parse("Week {section}/{module}").unwrap();
+        deduplicate_names(&mut elements, &template);
+
+        let folders: HashSet<_> = elements
+            .iter()
+            .map(|element| template.section_path(Path::new(""), &element.name, element.section))
+            .collect();
+        assert_eq!(folders.len(), 3);
+        // The suffix survives the template literal and the length limit
+        let folder = template.section_path(Path::new(""), &elements[1].name, 1);
+        assert!(folder.to_string_lossy().ends_with(" (1)"));
+        assert!(elements[2].name.ends_with(" (2)"));
+    }
+}
@@ -2,34 +2,37 @@ use std::str::FromStr;
 
 use url::Url;
 
-use super::*;
+use super::{content_types::AssignDates, *};
 
 #[derive(Debug, Deserialize)]
 pub struct Assign {
+    pub id: u64,
     pub instance: u64,
     pub name: String,
     pub url: String,
     pub description: Option<String>,
+    /// Json encoded, contains the dates of the assignment
+    pub customdata: Option<String>,
 }
 impl Download for Assign {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        let path = path.join(&self.name);
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
+        let path = config.path_template.module_path(path, &self.name);
         // Assignments don't provide most of their information via core_course_get_contents
         // Therefore we need to use mod_assign_get_submission_status instead
         match config.mod_assign_get_submission_status(self.instance).await {
-            Ok(assignment) => {
-                assignment.download(config.clone(), &path).await?;
+            Ok((assignment, raw)) => {
+                assignment.download(config.clone(), scope, &path).await?;
+                assignment
+                    .save_status(&config, &path, &self.name, &self.dates(), &raw)
+                    .await
+                    .context("Failed saving assignment status")?;
             }
             Err(e) => {
                 config
-                    .status_bar
-                    .register_err(
-                        &e.context(format!(
-                            "Failed checking assignment: url: {} instance: {} (This is most likely a deserialization issue)",
-                            &self.url, &self.instance,
-                        ))
-                        .to_string(),
-                    )
+                    .register_api_err(e.context(format!(
+                        "Failed checking assignment: url: {} instance: {}",
+                        &self.url, &self.instance,
+                    )))
                     .await
             }
         }
@@ -63,3 +66,13 @@ impl Download for Assign {
         Ok(())
     }
 }
+
+impl Assign {
+    /// Get the dates from the `customdata`, missing dates are left empty
+    pub fn dates(&self) -> AssignDates {
+        self.customdata
+            .as_deref()
+            .and_then(|customdata| serde_json::from_str(customdata).ok())
+            .unwrap_or_default()
+    }
+}
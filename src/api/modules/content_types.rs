@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use serde::Deserializer;
 use tracing::warn;
 use url::Url;
 
@@ -23,6 +24,7 @@ pub struct ContentFile {
     fileurl: String,
     timemodified: u64,
     filesize: Option<u64>,
+    mimetype: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -32,11 +34,43 @@ pub struct ContentUrl {
     timemodified: u64,
 }
 
+/// Dates as contained in the `customdata` of an assignment
+#[derive(Debug, Deserialize, Default)]
+pub struct AssignDates {
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub allowsubmissionsfromdate: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub duedate: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub cutoffdate: Option<u64>,
+}
+
+/// Moodle encodes the timestamps in `customdata` either as numbers or as strings
+fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timestamp {
+        Number(u64),
+        String(String),
+    }
+
+    let timestamp = match Option::<Timestamp>::deserialize(deserializer)? {
+        Some(Timestamp::Number(timestamp)) => Some(timestamp),
+        Some(Timestamp::String(timestamp)) => timestamp.parse().ok(),
+        None => None,
+    };
+    // A timestamp of 0 means the date is disabled
+    Ok(timestamp.filter(|timestamp| *timestamp != 0))
+}
+
 impl Download for Content {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         match self {
-            Content::File(content_file) => content_file.download(config, path).await,
-            Content::Url(content_url) => content_url.download(config, path).await,
+            Content::File(content_file) => content_file.download(config, scope, path).await,
+            Content::Url(content_url) => content_url.download(config, scope, path).await,
             Content::Unknown => {
                 warn!("Not syncing unknown Content type, create an issue if you want this added!");
                 Ok(())
@@ -46,14 +80,20 @@ impl Download for Content {
 }
 
 impl Download for ContentFile {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        // Check against regex filters
-        if config.check_filter(&self.filename).await? {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
+        let file_path = &self::assemble_path(path, &self.filepath, &self.filename);
+
+        // Check against filters
+        let filter_item = FilterItem {
+            path: Some(file_path),
+            name: &self.filename,
+            mime: self.mimetype.as_deref(),
+            size: self.filesize,
+        };
+        if config.check_filter(&filter_item, scope).await? {
             return Ok(());
         }
 
-        let file_path = &self::assemble_path(path, &self.filepath, &self.filename);
-
         let request = config
             .client
             .get(&self.fileurl)
@@ -81,16 +121,30 @@ impl Download for ContentFile {
 }
 
 impl Download for ContentUrl {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Check for youtube vidoes
         config
-            .queue_youtube_vidoes_extract(&self.fileurl, path.to_owned())
+            .queue_youtube_vidoes_extract(scope, &self.fileurl, path.to_owned())
             .await?;
         // Check for sciebo links
-        Config::extract_sciebo_download(config.clone(), &self.fileurl, path.to_owned()).await?;
+        Config::extract_sciebo_download(config.clone(), scope, &self.fileurl, path.to_owned())
+            .await?;
+
+        let file_name = sanitize_path_component(&self.filename);
+        let file_path = path.join(&file_name);
+
+        // Check against filters
+        let filter_item = FilterItem {
+            path: Some(&file_path),
+            name: &self.filename,
+            ..Default::default()
+        };
+        if config.check_filter(&filter_item, scope).await? {
+            return Ok(());
+        }
 
         // Create .html, that redirects to the url
-        let redirect_path = path.join(format!("{}.html", &self.filename));
+        let redirect_path = path.join(format!("{}.html", &file_name));
         let redirect_content = format!(
             r###"<!DOCTYPE html>
                 <html>
@@ -109,7 +163,6 @@ impl Download for ContentUrl {
             .await?;
 
         // Additionally save webpage
-        let file_path = path.join(&self.filename);
         config
             .save_page_with_timestamp(
                 &file_path,
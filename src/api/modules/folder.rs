@@ -2,18 +2,19 @@ use super::{content_types::Content, *};
 
 #[derive(Debug, Deserialize)]
 pub struct Folder {
+    pub id: u64,
     pub name: String,
     pub contents: Option<Vec<Content>>,
 }
 
 impl Download for Folder {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        let path = path.join(&self.name);
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
+        let path = config.path_template.module_path(path, &self.name);
         if let Some(contents) = &self.contents {
             // Create a task for each content
             let tasks = contents
                 .into_iter()
-                .map(|r| r.download(config.clone(), &path));
+                .map(|r| r.download(config.clone(), scope, &path));
             // Return an error if one occured
             for res in join_all(tasks).await {
                 res.context("Failed Resource")?;
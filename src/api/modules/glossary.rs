@@ -6,8 +6,8 @@ pub struct Glossary {
     pub name: String,
 }
 impl Download for Glossary {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        let file_path = path.join(self.name.to_string());
+    async fn download(&self, config: Arc<Config>, _scope: &FilterScope, path: &Path) -> Result<()> {
+        let file_path = path.join(sanitize_path_component(&self.name));
         let mut glossary_url = config.get_moodle_url().join("mod/glossary/print.php")?;
         glossary_url
             .query_pairs_mut()
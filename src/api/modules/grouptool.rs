@@ -6,12 +6,13 @@ use super::*;
 
 #[derive(Debug, Deserialize)]
 pub struct Grouptool {
+    pub id: u64,
     pub name: String,
     pub url: String,
 }
 impl Download for Grouptool {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        let file_path = path.join(self.name.to_string());
+    async fn download(&self, config: Arc<Config>, _scope: &FilterScope, path: &Path) -> Result<()> {
+        let file_path = path.join(sanitize_path_component(&self.name));
 
         let url = Url::from_str(&self.url)?;
 
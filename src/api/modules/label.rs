@@ -2,24 +2,36 @@ use super::*;
 
 #[derive(Debug, Deserialize)]
 pub struct Label {
+    pub id: u64,
     pub name: String,
     pub description: String,
 }
 
 impl Download for Label {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        // Check for youtube vidoes
-        config
-            .queue_youtube_vidoes_extract(&self.description, path.to_owned())
-            .await?;
-        // Check for sciebo links
-        Config::extract_sciebo_download(config.clone(), &self.description, path.to_owned()).await?;
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
+        // The label is a single file, named like the module folder would be
+        let module_path = config.path_template.module_path(path, &self.name);
+        let path = module_path.parent().unwrap_or(path);
+        let file_name = format!(
+            "{}.html",
+            module_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        );
 
-        let file_name = format!("{}.html", self.name);
-        let path = path.join(file_name);
+        // Check against filters
+        let file_path = path.join(&file_name);
+        let filter_item = FilterItem {
+            path: Some(&file_path),
+            name: &file_name,
+            mime: Some("text/html"),
+            ..Default::default()
+        };
+        if config.check_filter(&filter_item, scope).await? {
+            return Ok(());
+        }
 
-        config.write_file_contents(&path, &self.description).await?;
-
-        Ok(())
+        Config::save_html_summary(config, scope, &self.description, path, &file_name).await
     }
 }
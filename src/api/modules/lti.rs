@@ -24,7 +24,7 @@ pub struct Lti {
 }
 
 impl Download for Lti {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Check if we have an rwth-opencast video (only rwth opencast has an opencast icon)
         if self.modicon
             == "https://moodle.rwth-aachen.de/theme/image.php/boost_union_rwth/theme_boost_union_rwth/-1/opencast_episode?filtericon=1"
@@ -49,23 +49,17 @@ impl Download for Lti {
                 None => {
                     // We couldn't get the vid_id via the description, fallback to extracting it using a SessionCookie
                     // Inspired by <https://github.com/Romern/syncMyMoodle> (Thank you!)
-                    let cookie = match config.get_cookie().await {
-                        Some(cookie) => cookie,
+                    let url = format!(
+                        "https://moodle.rwth-aachen.de/mod/lti/launch.php?id={}&triggerview=0",
+                        &self.id
+                    );
+                    let response = match config.get_with_session(&url).await? {
+                        Some(response) => response,
                         None => {
                             config.status_bar.register_skipped().await;
                             return Ok(());
                         }
                     };
-                    let url = format!(
-                        "https://moodle.rwth-aachen.de/mod/lti/launch.php?id={}&triggerview=0",
-                        &self.id
-                    );
-                    let response = config
-                        .client
-                        .get(url)
-                        .header("Cookie", "MoodleSession=".to_string() + &cookie)
-                        .send()
-                        .await?;
 
                     let html = response.text().await?;
                     let document = Document::from(html.as_str());
@@ -99,10 +93,12 @@ impl Download for Lti {
                 ))?;
             }
 
-            let file_path = path.join(&self.name).with_extension("mp4");
+            let file_path = path
+                .join(sanitize_path_component(&self.name))
+                .with_extension("mp4");
 
             config
-                .queue_youtube_video(url, OutputType::File(file_path))
+                .queue_youtube_video(scope, url, OutputType::File(file_path))
                 .await
                 .context("Failed Opencast")?;
         }
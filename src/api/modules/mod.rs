@@ -61,21 +61,61 @@ pub enum Module {
     Unknown,
 }
 
+impl Module {
+    /// Name of the module (None for unknown modules)
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Module::Resource(a) => Some(&a.name),
+            Module::ApiUrl(a) => Some(&a.name),
+            Module::Folder(a) => Some(&a.name),
+            Module::Label(a) => Some(&a.name),
+            Module::Quiz(a) => Some(&a.name),
+            Module::Lti(a) => Some(&a.name),
+            Module::Page(a) => Some(&a.name),
+            Module::Glossary(a) => Some(&a.name),
+            Module::Vpl(a) => Some(&a.name),
+            Module::Assign(a) => Some(&a.name),
+            Module::Pdfannotator(a) => Some(&a.name),
+            Module::Grouptool(a) => Some(&a.name),
+            Module::Unknown => None,
+        }
+    }
+
+    /// Name and course module id of the module (None for unknown modules)
+    pub fn name_and_id_mut(&mut self) -> Option<(&mut String, u64)> {
+        match self {
+            Module::Resource(a) => Some((&mut a.name, a.id)),
+            Module::ApiUrl(a) => Some((&mut a.name, a.id)),
+            Module::Folder(a) => Some((&mut a.name, a.id)),
+            Module::Label(a) => Some((&mut a.name, a.id)),
+            Module::Quiz(a) => Some((&mut a.name, a.id)),
+            Module::Lti(a) => Some((&mut a.name, a.id)),
+            Module::Page(a) => Some((&mut a.name, a.id)),
+            Module::Glossary(a) => Some((&mut a.name, a.id)),
+            Module::Vpl(a) => Some((&mut a.name, a.id)),
+            Module::Assign(a) => Some((&mut a.name, a.id)),
+            Module::Pdfannotator(a) => Some((&mut a.name, a.id)),
+            Module::Grouptool(a) => Some((&mut a.name, a.id)),
+            Module::Unknown => None,
+        }
+    }
+}
+
 impl Download for Module {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         match &self {
-            Module::Resource(a) => a.download(config, path).await?,
-            Module::ApiUrl(a) => a.download(config, path).await?,
-            Module::Folder(a) => a.download(config, path).await?,
-            Module::Label(a) => a.download(config, path).await?,
-            Module::Quiz(a) => a.download(config, path).await?,
-            Module::Lti(a) => a.download(config, path).await?,
-            Module::Page(a) => a.download(config, path).await?,
-            Module::Glossary(a) => a.download(config, path).await?,
-            Module::Vpl(a) => a.download(config, path).await?,
-            Module::Assign(a) => a.download(config, path).await?,
-            Module::Pdfannotator(a) => a.download(config, path).await?,
-            Module::Grouptool(a) => a.download(config, path).await?,
+            Module::Resource(a) => a.download(config, scope, path).await?,
+            Module::ApiUrl(a) => a.download(config, scope, path).await?,
+            Module::Folder(a) => a.download(config, scope, path).await?,
+            Module::Label(a) => a.download(config, scope, path).await?,
+            Module::Quiz(a) => a.download(config, scope, path).await?,
+            Module::Lti(a) => a.download(config, scope, path).await?,
+            Module::Page(a) => a.download(config, scope, path).await?,
+            Module::Glossary(a) => a.download(config, scope, path).await?,
+            Module::Vpl(a) => a.download(config, scope, path).await?,
+            Module::Assign(a) => a.download(config, scope, path).await?,
+            Module::Pdfannotator(a) => a.download(config, scope, path).await?,
+            Module::Grouptool(a) => a.download(config, scope, path).await?,
             Module::Unknown => {}
         }
         Ok(())
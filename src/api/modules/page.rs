@@ -6,6 +6,7 @@ use super::{content_types::Content, *};
 
 #[derive(Debug, Deserialize)]
 pub struct Page {
+    pub id: u64,
     pub name: String,
     pub url: String,
     pub contents: Option<Vec<Content>>,
@@ -17,19 +18,29 @@ pub struct ContentsInfo {
 }
 
 impl Download for Page {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        let path = path.join(&self.name);
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
+        let path = config.path_template.module_path(path, &self.name);
         if let Some(contents) = &self.contents {
             // Create a task for each content
             let tasks = contents
                 .into_iter()
-                .map(|r| r.download(config.clone(), &path));
+                .map(|r| r.download(config.clone(), scope, &path));
             // Return an error if one occured
             for res in join_all(tasks).await {
                 res.context("Failed Resource")?;
             }
         }
 
+        // Check against filters
+        let filter_item = FilterItem {
+            path: Some(&path),
+            name: &self.name,
+            ..Default::default()
+        };
+        if config.check_filter(&filter_item, scope).await? {
+            return Ok(());
+        }
+
         let url = Url::from_str(&self.url)?;
         config
             .save_page_with_timestamp(&path, &url, self.contentsinfo.lastmodified)
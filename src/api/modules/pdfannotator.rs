@@ -2,11 +2,12 @@ use super::{content_types::Content, *};
 
 #[derive(Debug, Deserialize)]
 pub struct Pdfannotator {
-    // pub name: String,
+    pub id: u64,
+    pub name: String,
     pub contents: Option<Vec<Content>>,
 }
 impl Download for Pdfannotator {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
+    async fn download(&self, config: Arc<Config>, scope: &FilterScope, path: &Path) -> Result<()> {
         // Not needed, as we usually have only one file (could be changed in the future)
         // let path = path.join(&self.name);
 
@@ -14,7 +15,7 @@ impl Download for Pdfannotator {
             // Create a task for each content
             let tasks = contents
                 .into_iter()
-                .map(|r| r.download(config.clone(), &path));
+                .map(|r| r.download(config.clone(), scope, &path));
             // Return an error if one occured
             for res in join_all(tasks).await {
                 res.context("Failed Resource")?;
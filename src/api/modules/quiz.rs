@@ -20,27 +20,21 @@ pub struct Quiz {
 
 impl Download for Quiz {
     // Warning this downloader requires scraping and therefore is increadibly slow!
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        let path = path.join(&self.name);
+    async fn download(&self, config: Arc<Config>, _scope: &FilterScope, path: &Path) -> Result<()> {
+        let path = config.path_template.module_path(path, &self.name);
 
-        let cookie = match config.get_cookie().await {
-            Some(cookie) => cookie,
-            None => {
-                config.status_bar.register_skipped().await;
-                return Ok(());
-            }
-        };
         trace!(
             "Attempting to get available quiz attempts for quiz id: {} name: {}",
             self.id, self.name
         );
 
-        let response = config
-            .client
-            .get(&self.url)
-            .header("Cookie", "MoodleSession=".to_string() + &cookie)
-            .send()
-            .await?;
+        let response = match config.get_with_session(&self.url).await? {
+            Some(response) => response,
+            None => {
+                config.status_bar.register_skipped().await;
+                return Ok(());
+            }
+        };
 
         let html = response.text().await?;
         let document = Document::from(html.as_str());
@@ -14,9 +14,23 @@ pub struct Vpl {
     pub name: String,
 }
 impl Download for Vpl {
-    async fn download(&self, config: Arc<Config>, path: &Path) -> Result<()> {
-        let path = path.join(self.name.to_string() + ".vpl");
+    async fn download(&self, config: Arc<Config>, _scope: &FilterScope, path: &Path) -> Result<()> {
+        let path = config
+            .path_template
+            .module_path(path, &(self.name.to_string() + ".vpl"));
 
+        // Fetching the submission page first makes sure, that the session is (still) valid
+        let submission_page_url = self.get_url(&config, "mod/vpl/forms/submissionview.php")?;
+        let response = match config
+            .get_with_session(submission_page_url.as_str())
+            .await?
+        {
+            Some(response) => response.text().await?,
+            None => {
+                config.status_bar.register_skipped().await;
+                return Ok(());
+            }
+        };
         let cookie = match config.get_cookie().await {
             Some(cookie) => cookie,
             None => {
@@ -55,7 +69,6 @@ impl Download for Vpl {
         let description_page_path = path.join("description");
         let submission_page_path = path.join("submission");
         let description_page_url = self.get_url(&config, "mod/vpl/view.php")?;
-        let submission_page_url = self.get_url(&config, "mod/vpl/forms/submissionview.php")?;
 
         config
             .save_page(&description_page_path, &description_page_url)
@@ -65,14 +78,6 @@ impl Download for Vpl {
             .await?;
 
         // Part 2. Get own submission files
-        let response = config
-            .client
-            .get(submission_page_url)
-            .header("Cookie", "MoodleSession=".to_string() + &cookie)
-            .send()
-            .await?
-            .text()
-            .await?;
         let document = Document::from(response.as_str());
 
         let url_start = config
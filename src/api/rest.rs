@@ -1,4 +1,15 @@
-use super::{assign_submission::ModAssignGetSubmissionStatus, modules::Module, *};
+use serde_json::Value;
+
+use super::{
+    assign_submission::ModAssignGetSubmissionStatus,
+    calendar::{
+        CoreCalendarGetActionEventsByCourses, CoreCalendarGetCalendarEvents,
+        ModAssignGetAssignments, ModQuizGetQuizzesByCourses,
+    },
+    grades::GradereportUserGetGradeItems,
+    modules::Module,
+    *,
+};
 
 // Descriptions taken from generated moodle docs these can be accessed on any moodle instance with administrator rights via: http://example.com/admin/webservice/documentation.php
 #[derive(Debug, Deserialize)]
@@ -16,14 +27,43 @@ pub struct CoreEnrolGetUsersCourses {
     pub id: u64,
     /// Short name of course
     pub shortname: String,
+    /// Long name of course
+    #[serde(default)]
+    pub fullname: String,
+    /// Course category id
+    pub category: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+/// Return category details
+pub(super) struct CoreCourseGetCategories {
+    /// Category id
+    pub id: u64,
+    /// Category name
+    pub name: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CoreCourseGetContentsElement {
+    /// Section number
+    pub section: u64,
     pub name: String,
+    /// Section description (html)
+    pub summary: Option<String>,
     pub modules: Vec<Module>,
 }
 
+#[derive(Debug, Deserialize)]
+/// Get courses matching a specific field (id/shortname/idnumber/category)
+pub(super) struct CoreCourseGetCoursesByField {
+    pub courses: Vec<CourseByField>,
+}
+#[derive(Debug, Deserialize)]
+pub(super) struct CourseByField {
+    /// course summary (html)
+    pub summary: Option<String>,
+}
+
 impl Config {
     pub(super) async fn core_webservice_get_site_info(&self) -> Result<CoreWebserviceGetSiteInfo> {
         Ok(self
@@ -45,6 +85,24 @@ impl Config {
             .await?)
     }
 
+    pub(super) async fn core_course_get_categories(
+        &self,
+        category_ids: &[u64],
+    ) -> Result<Vec<CoreCourseGetCategories>> {
+        let category_ids = category_ids
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        self.api_request_json::<Vec<CoreCourseGetCategories>>(&[
+            ("wsfunction", "core_course_get_categories"),
+            ("criteria[0][key]", "ids"),
+            ("criteria[0][value]", &category_ids),
+            ("addsubcategories", "0"),
+        ])
+        .await
+    }
+
     pub async fn api_core_course_get_contents(
         &self,
         course_id: u64,
@@ -57,16 +115,37 @@ impl Config {
             .await?)
     }
 
+    /// Get courses by a field like `id` or `shortname`
+    pub(super) async fn core_course_get_courses_by_field(
+        &self,
+        field: &str,
+        value: &str,
+    ) -> Result<CoreCourseGetCoursesByField> {
+        self.api_request_json::<CoreCourseGetCoursesByField>(&[
+            ("wsfunction", "core_course_get_courses_by_field"),
+            ("field", field),
+            ("value", value),
+        ])
+        .await
+    }
+
+    /// Returns the deserialized submission status together with the full response
     pub(super) async fn mod_assign_get_submission_status(
         &self,
         assignid: u64,
-    ) -> Result<ModAssignGetSubmissionStatus> {
-        Ok(self
-            .api_request_json::<ModAssignGetSubmissionStatus>(&[
+    ) -> Result<(ModAssignGetSubmissionStatus, Value)> {
+        let raw: Value = self
+            .api_request_json(&[
                 ("wsfunction", "mod_assign_get_submission_status"),
                 ("assignid", &assignid.to_string()),
             ])
-            .await?)
+            .await?;
+        let status =
+            serde_json::from_value(raw.clone()).map_err(|error| ApiError::Deserialization {
+                function: "mod_assign_get_submission_status".to_string(),
+                error,
+            })?;
+        Ok((status, raw))
     }
 
     pub(super) async fn gradereport_user_get_grades_table(&self, course_id: u64) -> Result<String> {
@@ -92,6 +171,84 @@ impl Config {
             ])
             .send()
             .await?;
-        Ok(response.text().await?)
+        let body = response.text().await?;
+        if let Ok(exception) = serde_json::from_str(&body) {
+            return Err(ApiError::Exception(exception).into());
+        }
+        Ok(body)
+    }
+
+    pub(super) async fn gradereport_user_get_grade_items(
+        &self,
+        course_id: u64,
+    ) -> Result<GradereportUserGetGradeItems> {
+        self.api_request_json::<GradereportUserGetGradeItems>(&[
+            ("wsfunction", "gradereport_user_get_grade_items"),
+            ("courseid", &course_id.to_string()),
+            ("userid", &self.user_id.to_string()),
+        ])
+        .await
     }
+
+    pub(super) async fn core_calendar_get_calendar_events(
+        &self,
+        course_ids: &[u64],
+    ) -> Result<CoreCalendarGetCalendarEvents> {
+        let course_ids = indexed_params("events[courseids]", course_ids);
+        let mut query = vec![("wsfunction", "core_calendar_get_calendar_events")];
+        query.extend(course_ids.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        self.api_request_json::<CoreCalendarGetCalendarEvents>(&query)
+            .await
+    }
+
+    pub(super) async fn mod_assign_get_assignments(
+        &self,
+        course_ids: &[u64],
+    ) -> Result<ModAssignGetAssignments> {
+        let course_ids = indexed_params("courseids", course_ids);
+        let mut query = vec![("wsfunction", "mod_assign_get_assignments")];
+        query.extend(course_ids.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        self.api_request_json::<ModAssignGetAssignments>(&query)
+            .await
+    }
+
+    pub(super) async fn mod_quiz_get_quizzes_by_courses(
+        &self,
+        course_ids: &[u64],
+    ) -> Result<ModQuizGetQuizzesByCourses> {
+        let course_ids = indexed_params("courseids", course_ids);
+        let mut query = vec![("wsfunction", "mod_quiz_get_quizzes_by_courses")];
+        query.extend(course_ids.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        self.api_request_json::<ModQuizGetQuizzesByCourses>(&query)
+            .await
+    }
+
+    pub(super) async fn core_calendar_get_action_events_by_courses(
+        &self,
+        course_ids: &[u64],
+        time_from: u64,
+        time_to: u64,
+    ) -> Result<CoreCalendarGetActionEventsByCourses> {
+        let course_ids = indexed_params("courseids", course_ids);
+        let (time_from, time_to) = (time_from.to_string(), time_to.to_string());
+        let mut query = vec![
+            ("wsfunction", "core_calendar_get_action_events_by_courses"),
+            ("timesortfrom", time_from.as_str()),
+            ("timesortto", time_to.as_str()),
+            // Maximum amount of events per course
+            ("limitnum", "50"),
+        ];
+        query.extend(course_ids.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        self.api_request_json::<CoreCalendarGetActionEventsByCourses>(&query)
+            .await
+    }
+}
+
+/// Create array parameters (e.g. `courseids[0]=1&courseids[1]=2`)
+fn indexed_params(name: &str, values: &[u64]) -> Vec<(String, String)> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (format!("{}[{}]", name, i), value.to_string()))
+        .collect()
 }
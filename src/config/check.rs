@@ -0,0 +1,697 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use regex::Regex;
+use serde_yml::Value;
+use serde_yml::libyml::parser::{Event, Parser};
+
+use crate::Result;
+use crate::config::sync_config::{Config, Instance, Module, PageConversion};
+
+/// All top-level keys of the config (unknown keys are most likely typos)
+const CONFIG_KEYS: [&str; 18] = [
+    "wstoken",
+    "user_id",
+    "login",
+    "courses",
+    "instances",
+    "modules",
+    "grades",
+    "deadlines",
+    "update_strategy",
+    "chrome_executable",
+    "sciebo",
+    "youtube",
+    "page_conversion",
+    "path_template",
+    "dir",
+    "log_file",
+    "file_filters",
+    "filters",
+];
+
+/// Keys, that are given per instance, if the config has several `instances`
+const INSTANCE_KEYS: [&str; 4] = ["wstoken", "user_id", "login", "courses"];
+
+/// All keys of an item of `instances`
+const INSTANCE_ITEM_KEYS: [&str; 5] = ["name", "wstoken", "user_id", "login", "courses"];
+
+/// All keys of an item of `courses`
+const COURSE_KEYS: [&str; 9] = [
+    "id",
+    "name",
+    "path_template",
+    "modules",
+    "file_filters",
+    "filters",
+    "update_strategy",
+    "grades",
+    "page_conversion",
+];
+
+/// All keys of a rule of `filters`
+const FILTER_KEYS: [&str; 9] = [
+    "action",
+    "path",
+    "filename",
+    "extension",
+    "mime",
+    "min_size",
+    "max_size",
+    "module",
+    "course",
+];
+
+const YOUTUBE_KEYS: [&str; 3] = ["path", "params", "parallel_downloads"];
+
+const SHIBBOLETH_FIELDS_KEYS: [&str; 3] = ["username", "password", "totp"];
+
+/// All keys of the login of the given type (unknown types are reported by serde)
+fn login_keys(login_type: &str) -> Option<&'static [&'static str]> {
+    Some(match login_type {
+        "ApiOnly" => &["type", "url", "private_token"],
+        "Raw" => &["type", "url", "cookie"],
+        "Graphical" | "Headless" => &["type", "url"],
+        "UserPass" | "Cas" => &["type", "url", "username", "password"],
+        "Rwth" => &["type", "username", "password", "totp", "totp_secret"],
+        "Shibboleth" => &[
+            "type",
+            "url",
+            "username",
+            "password",
+            "totp_secret",
+            "fields",
+        ],
+        "OAuth2" => &["type", "url", "issuer"],
+        _ => return None,
+    })
+}
+
+/// A single problem found in the config
+#[derive(Debug)]
+pub struct ConfigProblem {
+    /// Line and column (starting at 1)
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+impl Display for ConfigProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((line, column)) = self.location {
+            write!(f, "{}:{}: ", line, column)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n    help: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format problems as `<config path>:<line>:<column>: <message>`
+pub fn format_problems(path: &Path, problems: &[ConfigProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| match problem.location {
+            Some(_) => format!("{}:{}", path.display(), problem),
+            None => format!("{}: {}", path.display(), problem),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse the config, collecting as many problems as possible
+pub fn parse_config(contents: &str) -> std::result::Result<Config, Vec<ConfigProblem>> {
+    let value: Value = match serde_yml::from_str(contents) {
+        Ok(value) => value,
+        Err(e) => return Err(vec![problem_from_serde(&e)]),
+    };
+
+    let problems = check_value(&Locations::parse(contents), &value);
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    serde_yml::from_str(contents).map_err(|e| vec![problem_from_serde(&e)])
+}
+
+/// Check the config for problems, that can be detected before deserializing
+/// (serde only reports the first one)
+fn check_value(locations: &Locations, value: &Value) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    if !value.is_mapping() {
+        return vec![ConfigProblem {
+            location: None,
+            message: "The config has to be a mapping of options".to_string(),
+            suggestion: Some("Create a new config with `moo-dl setup`".to_string()),
+        }];
+    }
+
+    check_keys(locations, value, "", &CONFIG_KEYS, None, &mut problems);
+
+    let has_instances = value.get("instances").is_some_and(Value::is_sequence);
+    let instances: Vec<_> = items(value, "", "instances").collect();
+    for (path, instance) in &instances {
+        check_keys(
+            locations,
+            instance,
+            path,
+            &INSTANCE_ITEM_KEYS,
+            Some("an instance"),
+            &mut problems,
+        );
+    }
+    // The top-level options and each instance have a login and courses
+    let scopes: Vec<_> = iter::once((String::new(), value))
+        .chain(instances.iter().cloned())
+        .collect();
+    for (path, login) in scopes
+        .iter()
+        .filter_map(|(path, scope)| Some((key_path(path, "login"), scope.get("login")?)))
+    {
+        let Some(login_type) = login.get("type").and_then(Value::as_str) else {
+            continue;
+        };
+        if let Some(keys) = login_keys(login_type) {
+            let scope = format!("the {} login", login_type);
+            check_keys(locations, login, &path, keys, Some(&scope), &mut problems);
+        }
+        if let Some(fields) = login.get("fields") {
+            check_keys(
+                locations,
+                fields,
+                &key_path(&path, "fields"),
+                &SHIBBOLETH_FIELDS_KEYS,
+                Some("the Shibboleth fields"),
+                &mut problems,
+            );
+        }
+    }
+    if let Some(youtube) = value.get("youtube") {
+        check_keys(
+            locations,
+            youtube,
+            "youtube",
+            &YOUTUBE_KEYS,
+            Some("youtube"),
+            &mut problems,
+        );
+    }
+
+    for key in INSTANCE_KEYS {
+        match (has_instances, value.get(key).is_some()) {
+            (false, false) => problems.push(ConfigProblem {
+                location: None,
+                message: format!("missing field `{}`", key),
+                suggestion: suggestion_from_message(&format!("missing field `{}`", key)),
+            }),
+            (true, true) => problems.push(ConfigProblem {
+                location: locations.key(key),
+                message: format!("`{}` can not be used together with `instances`", key),
+                suggestion: Some(format!("Set `{}` in each instance instead", key)),
+            }),
+            _ => {}
+        }
+    }
+    let mut names = Vec::new();
+    for (path, name) in instances
+        .iter()
+        .filter_map(|(path, instance)| Some((path, instance.get("name").and_then(Value::as_str)?)))
+    {
+        if names.contains(&name) {
+            problems.push(ConfigProblem {
+                location: locations.value(&key_path(path, "name")),
+                message: format!("The instance name `{}` is used twice", name),
+                suggestion: Some("Each instance is synced into a folder of its name".to_string()),
+            });
+        }
+        names.push(name);
+    }
+
+    // Course specific settings are checked the same way as the global ones
+    let courses = scopes
+        .iter()
+        .flat_map(|(path, scope)| items(scope, path, "courses"));
+    for (index, (path, scope)) in iter::once((String::new(), value))
+        .chain(courses)
+        .enumerate()
+    {
+        if index > 0 {
+            check_keys(
+                locations,
+                scope,
+                &path,
+                &COURSE_KEYS,
+                Some("a course"),
+                &mut problems,
+            );
+        }
+        check_modules(
+            locations,
+            scope.get("modules"),
+            &key_path(&path, "modules"),
+            &mut problems,
+        );
+        for (path, regex) in items(scope, &path, "file_filters") {
+            check_regex(locations, &path, regex, &mut problems);
+        }
+        for (path, rule) in items(scope, &path, "filters") {
+            check_keys(
+                locations,
+                rule,
+                &path,
+                &FILTER_KEYS,
+                Some("a filter"),
+                &mut problems,
+            );
+            check_modules(
+                locations,
+                rule.get("module"),
+                &key_path(&path, "module"),
+                &mut problems,
+            );
+            for key in ["path", "filename"] {
+                if let Some(regex) = rule.get(key) {
+                    check_regex(locations, &key_path(&path, key), regex, &mut problems);
+                }
+            }
+        }
+    }
+    problems.sort_by_key(|problem| problem.location);
+    problems
+}
+
+/// Check the modules of the list at `path`
+fn check_modules(
+    locations: &Locations,
+    modules: Option<&Value>,
+    path: &str,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    let modules = modules.and_then(Value::as_sequence).into_iter().flatten();
+    for (index, module) in modules.enumerate() {
+        if let Err(e) = serde_yml::from_value::<Module>(module.clone()) {
+            let name = module.as_str().unwrap_or_default();
+            problems.push(ConfigProblem {
+                location: locations.value(&index_path(path, index)),
+                message: format!("Unknown module `{}`", name),
+                suggestion: suggestion_from_message(&e.to_string()),
+            });
+        }
+    }
+}
+
+/// Check the regex at `path`
+fn check_regex(
+    locations: &Locations,
+    path: &str,
+    regex: &Value,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    let Some(regex) = regex.as_str() else {
+        return;
+    };
+    if let Err(e) = Regex::new(regex) {
+        problems.push(ConfigProblem {
+            location: locations.value(path),
+            message: format!("Invalid regex `{}`", regex),
+            suggestion: Some(e.to_string().lines().last().unwrap_or_default().to_string()),
+        });
+    }
+}
+
+/// Report the keys of the mapping at `path`, that are not `known` (`scope` describes the mapping)
+fn check_keys(
+    locations: &Locations,
+    mapping: &Value,
+    path: &str,
+    known: &[&str],
+    scope: Option<&str>,
+    problems: &mut Vec<ConfigProblem>,
+) {
+    let Some(mapping) = mapping.as_mapping() else {
+        return;
+    };
+    for key in mapping.keys().filter_map(Value::as_str) {
+        if known.contains(&key) {
+            continue;
+        }
+        problems.push(ConfigProblem {
+            location: locations.key(&key_path(path, key)),
+            message: match scope {
+                Some(scope) => format!("Unknown option `{}` in {}", key, scope),
+                None => format!("Unknown option `{}`", key),
+            },
+            suggestion: closest(key, known.iter().copied())
+                .map(|c| format!("Did you mean `{}`?", c)),
+        });
+    }
+}
+
+fn problem_from_serde(e: &serde_yml::Error) -> ConfigProblem {
+    let message = e.to_string();
+    // Remove the location from the message, as we show it in front
+    let message = match message.split_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    };
+    let location = e
+        .location()
+        .map(|location| (location.line(), location.column()))
+        // Missing fields are reported at the start of the surrounding mapping
+        .filter(|_| !message.contains("missing field"));
+    let suggestion = suggestion_from_message(&message);
+    ConfigProblem {
+        location,
+        message,
+        suggestion,
+    }
+}
+
+/// Create a suggestion from a serde error message
+fn suggestion_from_message(message: &str) -> Option<String> {
+    let words = backtick_words(message);
+    if message.contains("missing field") {
+        return words.first().map(|field| {
+            format!(
+                "Add the option `{}` (see example-config.yml for all options)",
+                field
+            )
+        });
+    }
+    // e.g. "unknown variant `Resorce`, expected one of `Resource`, `Folder`, ..."
+    let (unknown, expected) = words.split_first()?;
+    closest(unknown, expected.iter().copied())
+        .map(|c| format!("Did you mean `{}`?", c))
+        .or_else(|| {
+            (!expected.is_empty()).then(|| format!("Expected one of: {}", expected.join(", ")))
+        })
+}
+
+/// Get all words in `backticks`
+fn backtick_words(message: &str) -> Vec<&str> {
+    message.split('`').skip(1).step_by(2).collect()
+}
+
+/// Find the most similar candidate (if any is similar enough)
+fn closest<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            (
+                strsim::jaro_winkler(&word.to_lowercase(), &candidate.to_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|(similarity, _)| *similarity > 0.8)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Path of the value of `key` in the mapping at `path` (e.g. `courses[0].name`)
+fn key_path(path: &str, key: &str) -> String {
+    match path {
+        "" => key.to_string(),
+        path => format!("{}.{}", path, key),
+    }
+}
+
+/// Path of the item at `index` in the sequence at `path`
+fn index_path(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
+
+/// The items (and their paths) of the sequence `key` in the mapping at `path`
+fn items<'a>(
+    mapping: &'a Value,
+    path: &str,
+    key: &str,
+) -> impl Iterator<Item = (String, &'a Value)> + use<'a> {
+    let path = key_path(path, key);
+    mapping
+        .get(key)
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(move |(index, item)| (index_path(&path, index), item))
+}
+
+/// Lines and columns of the keys and values in the config, by their path
+///
+/// Taken from the yaml parser, so quoted and escaped values are located correctly.
+#[derive(Debug, Default)]
+struct Locations {
+    keys: HashMap<String, (usize, usize)>,
+    values: HashMap<String, (usize, usize)>,
+}
+
+/// A mapping or sequence, that is being parsed
+enum Collection {
+    /// `key` is the key of the next value (`None`, if the next scalar is a key)
+    Mapping {
+        path: String,
+        key: Option<String>,
+    },
+    Sequence {
+        path: String,
+        length: usize,
+    },
+}
+
+impl Locations {
+    fn parse(contents: &str) -> Locations {
+        let mut locations = Locations::default();
+        let mut parser = Parser::new(Cow::Borrowed(contents.as_bytes()));
+        let mut collections: Vec<Collection> = Vec::new();
+        // Invalid yaml is reported by serde, the locations just end there
+        while let Ok((event, mark)) = parser.parse_next_event() {
+            // The parser starts counting at 0
+            let location = (mark.line() as usize + 1, mark.column() as usize + 1);
+            let path = match (&event, collections.last_mut()) {
+                (Event::StreamEnd, _) => break,
+                (Event::StreamStart | Event::DocumentStart | Event::DocumentEnd, _) => continue,
+                (Event::SequenceEnd | Event::MappingEnd, _) => {
+                    collections.pop();
+                    continue;
+                }
+                (Event::Scalar(scalar), Some(Collection::Mapping { path, key }))
+                    if key.is_none() =>
+                {
+                    let name = String::from_utf8_lossy(&scalar.value).into_owned();
+                    locations.keys.insert(key_path(path, &name), location);
+                    *key = Some(name);
+                    continue;
+                }
+                // Other keys (e.g. sequences) are never valid options
+                (_, Some(Collection::Mapping { key, .. })) if key.is_none() => {
+                    *key = Some(String::new());
+                    "?".to_string()
+                }
+                (_, Some(Collection::Mapping { path, key })) => {
+                    key_path(path, &key.take().unwrap_or_default())
+                }
+                (_, Some(Collection::Sequence { path, length })) => {
+                    *length += 1;
+                    index_path(path, *length - 1)
+                }
+                (_, None) => String::new(),
+            };
+            locations.values.insert(path.clone(), location);
+            match event {
+                Event::MappingStart(_) => collections.push(Collection::Mapping { path, key: None }),
+                Event::SequenceStart(_) => {
+                    collections.push(Collection::Sequence { path, length: 0 })
+                }
+                _ => {}
+            }
+        }
+        locations
+    }
+
+    /// Location of the key of the value at `path`
+    fn key(&self, path: &str) -> Option<(usize, usize)> {
+        self.keys.get(path).copied()
+    }
+
+    /// Location of the value at `path`
+    fn value(&self, path: &str) -> Option<(usize, usize)> {
+        self.values.get(path).copied()
+    }
+}
+
+/// Check everything, that is referenced by the config (executables and folders)
+pub fn check_paths(config: &Config, contents: &str, config_path: &Path) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    let locations = Locations::parse(contents);
+    let config_dir = config_path.parent().unwrap_or(Path::new("."));
+
+    if let Some(youtube) = &config.youtube
+        && find_executable(&youtube.path).is_none()
+    {
+        problems.push(ConfigProblem {
+            location: locations.key("youtube"),
+            message: format!("yt-dlp was not found at `{}`", youtube.path.display()),
+            suggestion: Some(
+                "Install yt-dlp or set youtube.path to the yt-dlp executable".to_string(),
+            ),
+        });
+    }
+    // Courses may convert pages with single-file on their own
+    let courses = config
+        .courses
+        .iter()
+        .enumerate()
+        .map(|(index, course)| (index_path("courses", index), course))
+        .chain(config.instances.iter().enumerate().flat_map(
+            |(instance, Instance { courses, .. })| {
+                let path = format!("instances[{}].courses", instance);
+                courses
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, course)| (index_path(&path, index), course))
+            },
+        ));
+    let page_conversions = iter::once((String::new(), Some(&config.page_conversion)))
+        .chain(courses.map(|(path, course)| (path, course.page_conversion.as_ref())));
+    for (path, page_conversion) in page_conversions {
+        if let Some(PageConversion::SingleFile(executable)) = page_conversion
+            && find_executable(executable).is_none()
+        {
+            problems.push(ConfigProblem {
+                location: locations.key(&key_path(&path, "page_conversion")),
+                message: format!("single-file was not found at `{}`", executable.display()),
+                suggestion: Some(
+                    "Install single-file-cli or use `type: Standard` page conversion".to_string(),
+                ),
+            });
+        }
+    }
+    if let Some(chrome) = &config.chrome_executable
+        && find_executable(chrome).is_none()
+    {
+        problems.push(ConfigProblem {
+            location: locations.key("chrome_executable"),
+            message: format!("Chrome was not found at `{}`", chrome.display()),
+            suggestion: Some(
+                "Remove chrome_executable to detect the browser automatically".to_string(),
+            ),
+        });
+    }
+    if let Some(dir) = &config.dir
+        && let Err(e) = check_writable_dir(&config_dir.join(dir))
+    {
+        problems.push(ConfigProblem {
+            location: locations.key("dir"),
+            message: format!("The sync dir `{}` is not usable: {}", dir.display(), e),
+            suggestion: None,
+        });
+    }
+    if let Some(log_file) = &config.log_file
+        && let Err(e) = check_writable_dir(log_file.parent().unwrap_or(config_dir))
+    {
+        problems.push(ConfigProblem {
+            location: locations.key("log_file"),
+            message: format!(
+                "The log file `{}` can not be written: {}",
+                log_file.display(),
+                e
+            ),
+            suggestion: None,
+        });
+    }
+    problems
+}
+
+/// Find an executable, either by path or by name in `PATH`
+pub fn find_executable(executable: &Path) -> Option<PathBuf> {
+    if executable.components().count() > 1 {
+        return executable.is_file().then(|| executable.to_path_buf());
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .flat_map(|dir| {
+            let path = dir.join(executable);
+            [path.with_extension("exe"), path]
+        })
+        .find(|path| path.is_file())
+}
+
+/// Check, that a directory exists (or can be created) and is writable
+fn check_writable_dir(dir: &Path) -> Result<()> {
+    // Relative paths without a folder (e.g. `moo-dl.log`) are in the working directory
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    // Missing folders get created during the sync
+    let existing = dir
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| anyhow!("no parent folder exists"))?;
+    if !existing.is_dir() {
+        return Err(anyhow!("`{}` is not a folder", existing.display()));
+    }
+    // Permissions alone don't tell (e.g. ACLs, read-only mounts), so try it
+    let probe = existing.join(format!(".moo-dl-write-check-{}", std::process::id()));
+    fs::write(&probe, b"").with_context(|| format!("`{}` is not writable", existing.display()))?;
+    fs::remove_file(&probe).with_context(|| format!("Failed to remove `{}`", probe.display()))?;
+    Ok(())
+}
+
+/// Check the config and print all problems (`moo-dl config check`)
+pub fn check_config(config_path: &PathBuf) -> Result<()> {
+    let contents = fs::read_to_string(config_path).with_context(|| "Failed to read config file")?;
+    let problems = match parse_config(&contents) {
+        Ok(mut config) => {
+            config.resolve_relative_paths(config_path);
+            let mut problems = Vec::new();
+            // Secret commands may ask for a password or unlock a keyring, so they are not run
+            if let Err(e) = config.check_secrets() {
+                problems.push(ConfigProblem {
+                    location: None,
+                    message: format!("{:#}", e),
+                    suggestion: Some(
+                        "Check the env: and file: references of the secrets".to_string(),
+                    ),
+                });
+            }
+            problems.extend(check_paths(&config, &contents, config_path));
+            problems
+        }
+        Err(problems) => problems,
+    };
+
+    if problems.is_empty() {
+        println!("{}: The config is valid", config_path.display());
+        return Ok(());
+    }
+    println!("{}", format_problems(config_path, &problems));
+    Err(anyhow!("Found {} problem(s) in the config", problems.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations_of_quoted_values() {
+        let contents = "# file_filters: [\"(a\"]\nfile_filters: ['(a', \"(a\"]\ncourses:\n  - id: 1\n    filters:\n      - filename: \"\\\\d(\"\n        colour: red\n";
+        let locations = Locations::parse(contents);
+        assert_eq!(locations.value("file_filters[0]"), Some((2, 16)));
+        assert_eq!(locations.value("file_filters[1]"), Some((2, 22)));
+        assert_eq!(
+            locations.value("courses[0].filters[0].filename"),
+            Some((6, 19))
+        );
+        assert_eq!(locations.key("courses[0].filters[0].colour"), Some((7, 9)));
+        assert_eq!(locations.key("colour"), None);
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about = "A fast moodle course downloader")]
@@ -20,6 +20,246 @@ pub enum Command {
         config_path: PathBuf,
     },
 
-    #[clap(about = "Create a config file")]
-    Setup {},
+    #[clap(about = "Create a config file (without prompts, if --login is given)")]
+    Setup {
+        #[command(flatten)]
+        args: Box<SetupArgs>,
+    },
+
+    #[clap(about = "List upcoming deadlines")]
+    Deadlines {
+        #[clap(long, help = "Path to config", default_value = ".moo-dl-config.yml")]
+        config_path: PathBuf,
+
+        #[clap(long, help = "Number of days to look ahead", default_value_t = 14)]
+        days: u64,
+    },
+
+    #[clap(about = "List, add and remove the synced courses")]
+    Courses {
+        #[command(subcommand)]
+        command: CoursesCommand,
+    },
+
+    #[clap(about = "Manage the config file")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    #[clap(about = "Check the config for problems")]
+    Check {
+        #[clap(long, help = "Path to config", default_value = ".moo-dl-config.yml")]
+        config_path: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CoursesCommand {
+    #[clap(about = "List the enrolled courses and whether they are synced")]
+    List {
+        #[clap(long, help = "Path to config", default_value = ".moo-dl-config.yml")]
+        config_path: PathBuf,
+
+        #[clap(long, help = "Only list the courses of this instance")]
+        instance: Option<String>,
+    },
+
+    #[clap(about = "Add an enrolled course to the config")]
+    Add {
+        #[clap(help = "Id or short name of the course")]
+        course: String,
+
+        #[clap(long, help = "Name of the course folder (defaults to the short name)")]
+        name: Option<String>,
+
+        #[clap(
+            long,
+            help = "Instance to add the course to (configs with instances only)"
+        )]
+        instance: Option<String>,
+
+        #[clap(long, help = "Path to config", default_value = ".moo-dl-config.yml")]
+        config_path: PathBuf,
+    },
+
+    #[clap(about = "Remove a course from the config (the downloaded files are kept)")]
+    Remove {
+        #[clap(help = "Id or name of the course")]
+        course: String,
+
+        #[clap(
+            long,
+            help = "Instance to remove the course from (configs with instances only)"
+        )]
+        instance: Option<String>,
+
+        #[clap(long, help = "Path to config", default_value = ".moo-dl-config.yml")]
+        config_path: PathBuf,
+    },
+}
+
+/// Options of `moo-dl setup`, the prompts are skipped if `--login` is given
+///
+/// All other options (except `--output`) only apply without prompts, therefore they require `--login`.
+/// Unlike the prompts, the config references the secrets (`env:NAME`), unless `--write-secrets` is given.
+#[derive(Args, Debug)]
+pub struct SetupArgs {
+    #[clap(
+        long,
+        help = "Where to write the config",
+        default_value = ".moo-dl-config.yml"
+    )]
+    pub output: PathBuf,
+
+    #[clap(
+        long,
+        value_enum,
+        help = "Login method (skips all prompts, graphical, headless and OAuth2 logins need the prompts)"
+    )]
+    pub login: Option<SetupLogin>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Moodle url (e.g. https://moodle.example.com)"
+    )]
+    pub url: Option<String>,
+
+    #[clap(long, requires = "login", help = "Username of the login")]
+    pub username: Option<String>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Environment variable containing the password"
+    )]
+    pub password_env: Option<String>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Name of the RWTH TOTP token (e.g. TOTP12345678)"
+    )]
+    pub totp: Option<String>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Environment variable containing the TOTP secret"
+    )]
+    pub totp_secret_env: Option<String>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Environment variable containing the wstoken (api-only)"
+    )]
+    pub wstoken_env: Option<String>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Environment variable containing the private token of the mobile app (api-only)"
+    )]
+    pub private_token_env: Option<String>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Write the secrets into the config like the prompts do (default: env: references to the variables)"
+    )]
+    pub write_secrets: bool,
+
+    #[clap(long, requires = "login", help = "Sync all enrolled courses")]
+    pub all_courses: bool,
+
+    #[clap(
+        long,
+        requires = "login",
+        value_delimiter = ',',
+        help = "Ids or short names of the courses to sync"
+    )]
+    pub courses: Vec<String>,
+
+    #[clap(
+        long,
+        requires = "login",
+        value_delimiter = ',',
+        help = "Modules to sync (default: all except Quiz and Vpl)"
+    )]
+    pub modules: Vec<String>,
+
+    #[clap(
+        long,
+        requires = "login",
+        value_enum,
+        default_value = "archive",
+        help = "Update strategy"
+    )]
+    pub update_strategy: SetupUpdateStrategy,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Path of yt-dlp (default: yt-dlp, if it is found on PATH)"
+    )]
+    pub youtube: Option<PathBuf>,
+
+    #[clap(
+        long,
+        requires = "login",
+        conflicts_with = "youtube",
+        help = "Don't download youtube videos"
+    )]
+    pub no_youtube: bool,
+
+    #[clap(
+        long,
+        requires = "login",
+        value_enum,
+        help = "How pages are saved (default: single-file, if it is found on PATH)"
+    )]
+    pub page_conversion: Option<SetupPageConversion>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Path of single-file (default: found on PATH)"
+    )]
+    pub single_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        requires = "login",
+        help = "Path of chromium (default: found on PATH)"
+    )]
+    pub chrome_executable: Option<PathBuf>,
+}
+
+/// Login methods, that work without prompts
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SetupLogin {
+    ApiOnly,
+    UserPass,
+    Rwth,
+    Shibboleth,
+    Cas,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SetupUpdateStrategy {
+    None,
+    Update,
+    Archive,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SetupPageConversion {
+    Standard,
+    SinglePage,
+    SingleFile,
 }
@@ -0,0 +1,221 @@
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, de};
+
+use crate::config::sync_config::Module;
+
+/// A filter rule, that matches if all of its conditions match
+///
+/// Rules are checked in order and the first matching rule decides.
+/// If no rule matches, the item is downloaded, unless there are `Include` rules.
+///
+/// Example:
+/// ```yaml
+/// - action: Exclude
+///   extension: [mp4, mkv]
+///   min_size: 500MiB
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FilterRule {
+    pub action: FilterAction,
+    /// Regex on the full path of the file
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub path: Option<Regex>,
+    /// Regex on the file name (or url for videos and links)
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub filename: Option<Regex>,
+    /// File extensions without the dot (case-insensitive)
+    pub extension: Option<Vec<String>>,
+    /// MIME types, `video/*` matches all videos
+    pub mime: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub min_size: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub max_size: Option<u64>,
+    pub module: Option<Vec<Module>>,
+    /// Course ids
+    pub course: Option<Vec<u64>>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAction {
+    Include,
+    Exclude,
+}
+
+/// Course and module a file is downloaded for
+///
+/// The course is set by `Config::course_config`, the module is passed along with the config
+/// to the downloads of each module.
+#[derive(Debug, Default, Clone)]
+pub struct FilterScope {
+    pub course: Option<u64>,
+    pub module: Option<Module>,
+}
+
+/// A file (or link, video, ...) that is about to be downloaded
+#[derive(Debug, Default)]
+pub struct FilterItem<'a> {
+    pub path: Option<&'a Path>,
+    pub name: &'a str,
+    pub mime: Option<&'a str>,
+    pub size: Option<u64>,
+}
+
+impl FilterRule {
+    /// Whether the rule has conditions, that can only be checked for files
+    fn is_file_rule(&self) -> bool {
+        self.filename.is_some()
+            || self.extension.is_some()
+            || self.mime.is_some()
+            || self.min_size.is_some()
+            || self.max_size.is_some()
+    }
+
+    /// Check all conditions, conditions on unknown properties (e.g. the size) never match
+    fn matches(&self, item: &FilterItem, scope: &FilterScope) -> bool {
+        let path = item.path.map(|path| path.to_string_lossy());
+        let extension = Path::new(item.name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        self.path
+            .as_ref()
+            .is_none_or(|re| path.as_ref().is_some_and(|path| re.is_match(path)))
+            && self
+                .filename
+                .as_ref()
+                .is_none_or(|re| re.is_match(item.name))
+            && self.extension.as_ref().is_none_or(|extensions| {
+                extension.as_ref().is_some_and(|extension| {
+                    extensions
+                        .iter()
+                        .any(|e| e.trim_start_matches('.').to_lowercase() == *extension)
+                })
+            })
+            && self.mime.as_ref().is_none_or(|mimes| {
+                item.mime
+                    .is_some_and(|mime| mimes.iter().any(|pattern| mime_matches(pattern, mime)))
+            })
+            && self
+                .min_size
+                .is_none_or(|min| item.size.is_some_and(|size| size >= min))
+            && self
+                .max_size
+                .is_none_or(|max| item.size.is_some_and(|size| size <= max))
+            && self.module.as_ref().is_none_or(|modules| {
+                scope
+                    .module
+                    .as_ref()
+                    .is_some_and(|module| modules.contains(module))
+            })
+            && self
+                .course
+                .as_ref()
+                .is_none_or(|courses| scope.course.is_some_and(|course| courses.contains(&course)))
+    }
+}
+
+/// Get the reason, why a file should be skipped (None if it should be downloaded)
+pub fn file_skip_reason(
+    rules: &[FilterRule],
+    item: &FilterItem,
+    scope: &FilterScope,
+) -> Option<String> {
+    match rules
+        .iter()
+        .enumerate()
+        .find(|(_, rule)| rule.matches(item, scope))
+    {
+        Some((index, rule)) if rule.action == FilterAction::Exclude => {
+            Some(format!("excluded by filter rule {}", index + 1))
+        }
+        Some(_) => None,
+        None if rules
+            .iter()
+            .any(|rule| rule.action == FilterAction::Include) =>
+        {
+            Some("not included by any filter rule".to_string())
+        }
+        None => None,
+    }
+}
+
+/// Get the reason, why a whole module should be skipped (None if it should be downloaded)
+///
+/// Only rules without file specific conditions are considered,
+/// the remaining rules are checked for the single files of the module.
+pub fn module_skip_reason(
+    rules: &[FilterRule],
+    item: &FilterItem,
+    scope: &FilterScope,
+) -> Option<String> {
+    rules
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| !rule.is_file_rule())
+        .find(|(_, rule)| rule.matches(item, scope))
+        .filter(|(_, rule)| rule.action == FilterAction::Exclude)
+        .map(|(index, _)| format!("excluded by filter rule {}", index + 1))
+}
+
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(main_type) => mime
+            .split_once('/')
+            .is_some_and(|(mime_main_type, _)| mime_main_type.eq_ignore_ascii_case(main_type)),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let regex: String = Deserialize::deserialize(deserializer)?;
+    Regex::new(&regex)
+        .map(Some)
+        .map_err(|e| de::Error::custom(format!("Failed to compile regex '{}': {}", regex, e)))
+}
+
+/// Deserialize a size in bytes, either as a number or with a unit (e.g. `10MB`, `1.5GiB`)
+fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Number(u64),
+        String(String),
+    }
+
+    match Size::deserialize(deserializer)? {
+        Size::Number(size) => Ok(Some(size)),
+        Size::String(size) => parse_size(&size)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("Invalid size '{}'", size))),
+    }
+}
+
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let unit_start = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(unit_start);
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000 * 1000,
+        "gb" => 1000 * 1000 * 1000,
+        "k" | "kib" => 1024,
+        "m" | "mib" => 1024 * 1024,
+        "g" | "gib" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    Some((number * multiplier as f64) as u64)
+}
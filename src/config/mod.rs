@@ -1,2 +1,7 @@
+pub mod check;
 pub mod cli;
+pub mod filter;
+pub mod path_template;
+pub mod secret;
 pub mod sync_config;
+pub mod yaml;
@@ -0,0 +1,209 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Deserializer, de};
+
+use crate::Result;
+use crate::download::sanitize_path_component;
+
+/// Template for the folders of sections and modules inside of a course
+///
+/// Example: `{section_index:02} - {section}/{module}`
+///
+/// Placeholders:
+/// * `{section}` - name of the section
+/// * `{section_index}` - number of the section (`{section_index:02}` pads it to two digits)
+/// * `{module}` - name of the module (only in segments after the section segments)
+///
+/// Only folders are templated: The files of a module keep their names (and the folders moodle
+/// gives them) inside the module folder. A trailing `/{file}` is accepted for older configs.
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    section: Vec<Vec<TemplatePart>>,
+    module: Vec<Vec<TemplatePart>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Section,
+    SectionIndex { width: usize },
+    Module,
+}
+
+impl Default for PathTemplate {
+    fn default() -> Self {
+        PathTemplate::parse("{section}/{module}").unwrap()
+    }
+}
+
+impl PathTemplate {
+    pub fn parse(template: &str) -> Result<PathTemplate> {
+        let mut segments: Vec<&str> = template.split('/').collect();
+        if segments.len() > 1 && segments.last() == Some(&"{file}") {
+            segments.pop();
+        }
+
+        let mut path_template = PathTemplate {
+            section: Vec::new(),
+            module: Vec::new(),
+        };
+        for segment in segments {
+            let parts = parse_segment(segment)?;
+            if parts.is_empty() {
+                return Err(anyhow!("The path template contains an empty segment"));
+            }
+            let is_module = parts.contains(&TemplatePart::Module);
+            let is_section = parts.iter().any(|part| {
+                matches!(
+                    part,
+                    TemplatePart::Section | TemplatePart::SectionIndex { .. }
+                )
+            });
+
+            if is_module && is_section {
+                return Err(anyhow!(
+                    "The segment \"{}\" mixes section and module placeholders",
+                    segment
+                ));
+            } else if is_module {
+                path_template.module.push(parts);
+            } else if path_template.module.is_empty() {
+                path_template.section.push(parts);
+            } else {
+                return Err(anyhow!(
+                    "The segment \"{}\" has to come before the module segments",
+                    segment
+                ));
+            }
+        }
+
+        // Modules store fixed file names (e.g. "description"), therefore they need their own folder
+        if path_template.module.is_empty() {
+            return Err(anyhow!(
+                "The path template has to contain a \"{{module}}\" segment"
+            ));
+        }
+        Ok(path_template)
+    }
+
+    /// Whether the section folders contain the name of the section
+    pub fn has_section_name(&self) -> bool {
+        self.section
+            .iter()
+            .any(|segment| segment.contains(&TemplatePart::Section))
+    }
+
+    /// Get the folder of a section
+    pub fn section_path(&self, path: &Path, section: &str, section_index: u64) -> PathBuf {
+        let mut path = path.to_path_buf();
+        for segment in &self.section {
+            path.push(render_segment(segment, section, section_index));
+        }
+        path
+    }
+
+    /// Get the folder of a module in a section folder (as created by `section_path`)
+    pub fn module_path(&self, path: &Path, module: &str) -> PathBuf {
+        let mut path = path.to_path_buf();
+        for segment in &self.module {
+            path.push(render_segment(segment, module, 0));
+        }
+        path
+    }
+}
+
+fn parse_segment(segment: &str) -> Result<Vec<TemplatePart>> {
+    let mut parts = Vec::new();
+    let mut rest = segment;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            parts.push(TemplatePart::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Unclosed \"{{\" in path template segment \"{}\"", segment))?
+            + start;
+
+        let placeholder = &rest[start + 1..end];
+        let (name, format) = match placeholder.split_once(':') {
+            Some((name, format)) => (name, Some(format)),
+            None => (placeholder, None),
+        };
+        parts.push(match (name, format) {
+            ("section", None) => TemplatePart::Section,
+            ("module", None) => TemplatePart::Module,
+            ("section_index", None) => TemplatePart::SectionIndex { width: 0 },
+            ("file", None) => {
+                return Err(anyhow!(
+                    "The path template only covers folders, \"{{file}}\" may only be the last segment"
+                ));
+            }
+            ("section_index", Some(format)) => TemplatePart::SectionIndex {
+                width: format
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid width \"{}\" for section_index", format))?,
+            },
+            _ => {
+                return Err(anyhow!(
+                    "Unknown placeholder \"{{{}}}\" in path template",
+                    placeholder
+                ));
+            }
+        });
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Literal(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+fn render_segment(segment: &[TemplatePart], name: &str, index: u64) -> String {
+    let rendered: String = segment
+        .iter()
+        .map(|part| match part {
+            TemplatePart::Literal(literal) => literal.clone(),
+            TemplatePart::Section | TemplatePart::Module => name.to_string(),
+            TemplatePart::SectionIndex { width } => format!("{:0width$}", index, width = width),
+        })
+        .collect();
+    sanitize_path_component(&rendered)
+}
+
+impl<'de> Deserialize<'de> for PathTemplate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let template = String::deserialize(deserializer)?;
+        PathTemplate::parse(&template).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_segment_is_optional() {
+        let template = PathTemplate::parse("{section_index:02} - {section}/{module}").unwrap();
+        let old = PathTemplate::parse("{section_index:02} - {section}/{module}/{file}").unwrap();
+        let path = template.section_path(Path::new("course"), "Week: 1", 3);
+        assert_eq!(path, old.section_path(Path::new("course"), "Week: 1", 3));
+        assert_eq!(path, Path::new("course/03 - Week_ 1"));
+        assert_eq!(
+            template.module_path(&path, "Slides"),
+            Path::new("course/03 - Week_ 1/Slides")
+        );
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!(PathTemplate::parse("{file}").is_err());
+        assert!(PathTemplate::parse("{section}/{file}/{module}").is_err());
+        assert!(PathTemplate::parse("{section}").is_err());
+        assert!(PathTemplate::parse("{module}/{section}").is_err());
+        assert!(PathTemplate::parse("{section}/{module}/").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::fs;
+use std::io::{self, Write};
+use std::process::Command;
+use std::sync::RwLock;
+
+use anyhow::{Context, anyhow};
+use once_cell::sync::Lazy;
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::Result;
+
+/// Secrets, that get replaced in all tracing output
+static SECRETS: Lazy<RwLock<Vec<String>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Very short values would redact unrelated output
+const MIN_SECRET_LENGTH: usize = 4;
+
+/// Resolve a secret reference from the config
+///
+/// Supported references:
+/// * `env:NAME` - environment variable
+/// * `file:/path/to/file` - contents of a file (without trailing newline)
+/// * `command:pass show moodle` - output of a command (e.g. a password manager, the system keyring is
+///   read this way as well, e.g. `command:secret-tool lookup service moodle`)
+///
+/// Anything else is used as is.
+pub fn resolve_secret(value: &str) -> Result<String> {
+    let secret = if let Some(name) = value.strip_prefix("env:") {
+        std::env::var(name).with_context(|| format!("Environment variable {} is not set", name))?
+    } else if let Some(path) = value.strip_prefix("file:") {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read secret file {}", path))?
+            .trim_end_matches(['\r', '\n'])
+            .to_string()
+    } else if let Some(command) = value.strip_prefix("command:") {
+        run_secret_command(command)?
+    } else {
+        value.to_string()
+    };
+
+    register_secret(&secret);
+    Ok(secret)
+}
+
+/// Check a secret reference like `resolve_secret`, but without running `command:` references
+pub fn check_secret(value: &str) -> Result<String> {
+    match value.starts_with("command:") {
+        true => Ok(value.to_string()),
+        false => resolve_secret(value),
+    }
+}
+
+fn run_secret_command(command: &str) -> Result<String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .with_context(|| format!("Failed to run secret command: {}", command))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Secret command \"{}\" failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?
+        .trim_end_matches(['\r', '\n'])
+        .to_string())
+}
+
+/// Redact a value (e.g. a session cookie or token) from all further tracing output
+///
+/// The escaped form is redacted as well, as it appears in `{:?}` output (e.g. of the config).
+pub fn register_secret(secret: &str) {
+    if secret.len() < MIN_SECRET_LENGTH {
+        return;
+    }
+    let debug = format!("{:?}", secret);
+    let escaped = &debug[1..debug.len() - 1];
+    let mut secrets = SECRETS.write().unwrap_or_else(|e| e.into_inner());
+    for secret in [secret, escaped] {
+        if !secrets.iter().any(|known| known == secret) {
+            secrets.push(secret.to_string());
+        }
+    }
+}
+
+/// Replace all registered secrets
+pub fn redact(text: &str) -> String {
+    let secrets = SECRETS.read().unwrap_or_else(|e| e.into_inner());
+    secrets.iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret, "[redacted]")
+    })
+}
+
+/// Writer for tracing, that redacts all registered secrets
+pub struct RedactingMakeWriter<M>(pub M);
+
+pub struct RedactingWriter<W>(W);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    // Events get written at once, therefore secrets are not split between writes
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .write_all(redact(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_debug_output() {
+        let secret = r#"pa"ss\wo'rd"#;
+        register_secret(secret);
+        let debug = format!("{:?}", vec![secret]);
+        assert_eq!(redact(&debug), r#"["[redacted]"]"#);
+        assert_eq!(redact(secret), "[redacted]");
+    }
+}
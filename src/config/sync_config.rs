@@ -1,82 +1,115 @@
 use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
-use anyhow::Context;
+use anyhow::{Context, anyhow};
 use async_channel::{Receiver, Sender};
 use regex::Regex;
 use reqwest::Client;
-use serde::{Deserialize, Deserializer};
-use tokio::sync::RwLock;
+use serde::{Deserialize, Deserializer, de};
+use tokio::sync::{Mutex, RwLock};
 use tracing::debug;
 use url::Url;
 
 use web2pdf_lib::Browser;
 
 use crate::Result;
+use crate::config::check::{format_problems, parse_config};
+use crate::config::filter::{FilterRule, FilterScope};
+use crate::config::path_template::PathTemplate;
+use crate::config::secret::{check_secret, resolve_secret};
+use crate::download::sanitize_path_component;
 use crate::download::youtube::YoutubeVideo;
 use crate::status_bar::StatusBar;
 
 pub fn read_config(path: &PathBuf) -> Result<Config> {
     let contents = fs::read_to_string(&path).with_context(|| "Failed to read config file")?;
-    let mut config: Config = serde_yml::from_str(&contents)
-        .with_context(|| "Could not parse config (There is most likely an error in the config)")?;
-
-    if let Some(file_path) = &config.log_file {
-        let log_path = {
-            if file_path.is_absolute() {
-                file_path
-            } else {
-                let mut config_path = path.clone();
-                let _ = config_path.pop();
-                &config_path.join(file_path)
-            }
-        };
-        config.log_file = Some(log_path.into());
-    }
+    let mut config = parse_config(&contents).map_err(|problems| {
+        anyhow!(
+            "Invalid config (run `moo-dl config check` to check it completely):\n{}",
+            format_problems(path, &problems)
+        )
+    })?;
+    config.resolve_relative_paths(path);
+    config.resolve_secrets()?;
 
     debug!("Read config: {:?}", config);
     Ok(config)
 }
 
 /// Top-level configuration structure
-#[derive(Debug, Deserialize, Default)]
+///
+/// Clones share the runtime state (login, browser, queues, status bar)
+#[derive(Debug, Deserialize, Default, Clone)]
 pub struct Config {
+    // wstoken, user_id, login and courses are only optional with `instances` (see `config::check`)
+    #[serde(default)]
     pub wstoken: String,
+    #[serde(default)]
     pub user_id: u64,
+    #[serde(default)]
     pub login: Login,
     #[serde(skip)]
     pub cookie: Arc<RwLock<LoginState>>,
+    /// Held while logging in again, so concurrent requests with an expired session only log in once
+    #[serde(skip)]
+    pub relogin: Arc<Mutex<()>>,
+    /// Where the session cookie is cached between runs (set for syncs)
+    #[serde(skip)]
+    pub session_cache: Option<PathBuf>,
+    /// Where the mobile app tokens of the OAuth2 and headless logins are stored (set for syncs)
+    #[serde(skip)]
+    pub mobile_tokens: Option<PathBuf>,
+    #[serde(default)]
     pub courses: Vec<Course>,
+    /// Several moodle instances (or accounts), each synced into its own subdirectory
+    #[serde(default)]
+    pub instances: Vec<Instance>,
+    /// Position of the instance in `instances` (set for instance configs)
+    #[serde(skip)]
+    pub instance_index: Option<usize>,
     pub modules: HashSet<Module>,
     pub grades: bool,
+    /// Saving `deadlines.ics` is on, unless disabled
+    #[serde(default = "default_true")]
+    pub deadlines: bool,
     pub update_strategy: UpdateStrategy,
     pub chrome_executable: Option<PathBuf>,
     #[serde(skip, default)]
-    pub chromium: RwLock<ChromiumState>,
+    pub chromium: Arc<RwLock<ChromiumState>>,
     pub sciebo: bool,
     pub youtube: Option<Youtube>,
     #[serde(skip, default)]
     pub youtube_queue: YoutubeQueue,
     pub page_conversion: PageConversion,
+    #[serde(default)]
+    pub path_template: PathTemplate,
     pub dir: Option<PathBuf>,
     pub log_file: Option<PathBuf>,
     #[serde(deserialize_with = "deserialize_file_filters")]
     pub file_filters: Vec<Regex>,
+    #[serde(default)]
+    pub filters: Vec<FilterRule>,
+    /// Course the config is used for (the filters get the module from the downloads)
+    #[serde(skip)]
+    pub filter_scope: FilterScope,
     #[serde(skip)]
     pub status_bar: Arc<StatusBar>,
     #[serde(skip, default = "create_standard_client")]
     pub client: Client,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 #[serde(tag = "type")]
 pub enum Login {
     // Provide api capabilities only
     ApiOnly {
         url: Url,
+        /// Private token of the mobile app, allows getting a session via an autologin key
+        #[serde(default)]
+        private_token: Option<String>,
     },
     Raw {
         url: Url,
@@ -85,6 +118,10 @@ pub enum Login {
     Graphical {
         url: Url,
     },
+    /// Log in on another device (e.g. when running on a server via ssh)
+    Headless {
+        url: Url,
+    },
     UserPass {
         url: Url,
         username: String,
@@ -98,18 +135,89 @@ pub enum Login {
         totp: String,
         totp_secret: String,
     },
+    /// Login via an Apereo CAS server
+    Cas {
+        url: Url,
+        username: String,
+        password: String,
+    },
+    /// Generic SAML login via a Shibboleth identity provider
+    Shibboleth {
+        url: Url,
+        username: String,
+        password: String,
+        /// Only needed, if the identity provider asks for a TOTP code
+        #[serde(default)]
+        totp_secret: Option<String>,
+        /// Names of the identity provider's form fields
+        #[serde(default)]
+        fields: ShibbolethFields,
+    },
+    /// Login via an OAuth2 provider of moodle (e.g. Microsoft, Google, Keycloak)
+    OAuth2 {
+        url: Url,
+        /// Name of the provider on the moodle login page (only needed, if there are several)
+        #[serde(default)]
+        issuer: Option<String>,
+    },
 }
 impl Default for Login {
     /// Warning: this is a dummy
     fn default() -> Self {
         let url = Url::from_str("https://dummy.example.com/").unwrap();
-        Login::ApiOnly { url }
+        Login::ApiOnly {
+            url,
+            private_token: None,
+        }
+    }
+}
+impl Login {
+    /// All fields, that contain credentials
+    fn secrets_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Login::ApiOnly { private_token, .. } => private_token.iter_mut().collect(),
+            Login::Graphical { .. } | Login::Headless { .. } | Login::OAuth2 { .. } => vec![],
+            Login::Raw { cookie, .. } => vec![cookie],
+            Login::UserPass { password, .. } | Login::Cas { password, .. } => vec![password],
+            Login::Rwth {
+                password,
+                totp_secret,
+                ..
+            } => vec![password, totp_secret],
+            Login::Shibboleth {
+                password,
+                totp_secret,
+                ..
+            } => std::iter::once(password).chain(totp_secret).collect(),
+        }
     }
 }
 pub fn rwth_url() -> Url {
     Url::from_str("https://moodle.rwth-aachen.de/").unwrap()
 }
 
+fn default_true() -> bool {
+    true
+}
+
+/// Form field names of a Shibboleth identity provider (the defaults match a standard Shibboleth IdP)
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShibbolethFields {
+    pub username: String,
+    pub password: String,
+    pub totp: String,
+}
+impl Default for ShibbolethFields {
+    fn default() -> Self {
+        ShibbolethFields {
+            username: "j_username".to_string(),
+            password: "j_password".to_string(),
+            totp: "j_tokenNumber".to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub enum LoginState {
     #[default]
@@ -120,15 +228,142 @@ pub enum LoginState {
     },
 }
 
-/// Course configuration (currently only room_id is provided).
-#[derive(Debug, Deserialize)]
+/// A moodle instance (or account) of a config with several `instances`
+///
+/// All other settings are shared by the instances
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Instance {
+    /// Name of the subdirectory
+    pub name: String,
+    pub wstoken: String,
+    pub user_id: u64,
+    pub login: Login,
+    pub courses: Vec<Course>,
+}
+
+/// Course configuration
+///
+/// All optional settings override the global ones for this course
+#[derive(Debug, Deserialize, Clone)]
 pub struct Course {
     pub id: u64,
     pub name: String,
+    pub path_template: Option<PathTemplate>,
+    pub modules: Option<HashSet<Module>>,
+    #[serde(default, deserialize_with = "deserialize_optional_file_filters")]
+    pub file_filters: Option<Vec<Regex>>,
+    pub filters: Option<Vec<FilterRule>>,
+    pub update_strategy: Option<UpdateStrategy>,
+    pub grades: Option<bool>,
+    pub page_conversion: Option<PageConversion>,
+}
+
+impl Config {
+    /// Make paths relative to the config file absolute
+    pub fn resolve_relative_paths(&mut self, config_path: &Path) {
+        if let Some(file_path) = &self.log_file
+            && file_path.is_relative()
+        {
+            let config_dir = config_path.parent().unwrap_or(Path::new("."));
+            self.log_file = Some(config_dir.join(file_path));
+        }
+    }
+
+    /// Resolve secret references (e.g. `env:MOODLE_PASS`) and redact the secrets from the tracing output
+    pub fn resolve_secrets(&mut self) -> Result<()> {
+        self.resolve_secrets_with(resolve_secret)
+    }
+
+    /// Check the secret references, without running commands (used by `moo-dl config check`)
+    pub fn check_secrets(&mut self) -> Result<()> {
+        self.resolve_secrets_with(check_secret)
+    }
+
+    fn resolve_secrets_with(
+        &mut self,
+        resolve_secret: impl Fn(&str) -> Result<String>,
+    ) -> Result<()> {
+        self.wstoken = resolve_secret(&self.wstoken).context("Failed resolving the wstoken")?;
+        for secret in self.login.secrets_mut() {
+            *secret = resolve_secret(secret).context("Failed resolving a login secret")?;
+        }
+        for instance in &mut self.instances {
+            let context = || format!("Instance: {}", instance.name);
+            instance.wstoken = resolve_secret(&instance.wstoken)
+                .context("Failed resolving the wstoken")
+                .with_context(context)?;
+            for secret in instance.login.secrets_mut() {
+                *secret = resolve_secret(secret)
+                    .context("Failed resolving a login secret")
+                    .with_context(context)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create the config of every instance and the folder it is synced into
+    ///
+    /// Without `instances` this is the config itself, synced into `path`.
+    /// The instances share the status bar, log, client and youtube queue, but log in separately.
+    pub fn instance_configs(&self, path: &Path) -> Vec<(Config, PathBuf)> {
+        if self.instances.is_empty() {
+            return vec![(self.clone(), path.to_path_buf())];
+        }
+        self.instances
+            .iter()
+            .enumerate()
+            .map(|(index, instance)| {
+                let mut instance_config = self.clone();
+                instance_config.wstoken = instance.wstoken.clone();
+                instance_config.user_id = instance.user_id;
+                instance_config.login = instance.login.clone();
+                instance_config.courses = instance.courses.clone();
+                instance_config.instances = Vec::new();
+                instance_config.instance_index = Some(index);
+                // The login state and the browser (holding the session cookie) belong to the instance
+                instance_config.cookie = Arc::default();
+                instance_config.relogin = Arc::default();
+                instance_config.chromium = Arc::default();
+                (
+                    instance_config,
+                    path.join(sanitize_path_component(&instance.name)),
+                )
+            })
+            .collect()
+    }
+
+    /// Create the config for a single course (applies the course specific overrides)
+    pub fn course_config(config: &Arc<Config>, course: &Course) -> Arc<Config> {
+        let mut course_config = Config::clone(config);
+        course_config.filter_scope.course = Some(course.id);
+        if let Some(path_template) = &course.path_template {
+            course_config.path_template = path_template.clone();
+        }
+        if let Some(modules) = &course.modules {
+            course_config.modules = modules.clone();
+        }
+        if let Some(file_filters) = &course.file_filters {
+            course_config.file_filters = file_filters.clone();
+        }
+        if let Some(filters) = &course.filters {
+            course_config.filters = filters.clone();
+        }
+        if let Some(update_strategy) = &course.update_strategy {
+            course_config.update_strategy = update_strategy.clone();
+        }
+        if let Some(grades) = course.grades {
+            course_config.grades = grades;
+        }
+        if let Some(page_conversion) = &course.page_conversion {
+            course_config.page_conversion = page_conversion.clone();
+        }
+        Arc::new(course_config)
+    }
 }
 
 // List of supported modules
-#[derive(Debug, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Hash, PartialEq, Eq, Clone)]
 pub enum Module {
     Resource,
     Folder,
@@ -148,7 +383,7 @@ pub enum Module {
 }
 
 // Update strategy
-#[derive(Debug, Deserialize, Hash, PartialEq, Eq, Default)]
+#[derive(Debug, Deserialize, Hash, PartialEq, Eq, Default, Clone)]
 pub enum UpdateStrategy {
     #[default]
     None,
@@ -165,14 +400,14 @@ pub enum ChromiumState {
 }
 
 // Optional config to enable extraction and download of youtube videos
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Youtube {
     pub path: PathBuf,
     pub params: Vec<String>,
     pub parallel_downloads: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct YoutubeQueue {
     pub sender: Sender<Arc<YoutubeVideo>>,
     pub receiver: Receiver<Arc<YoutubeVideo>>,
@@ -188,7 +423,7 @@ impl Default for YoutubeQueue {
 }
 
 /// Page conversion settings – only one of these should be set.
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, Clone)]
 #[serde(tag = "type", content = "path")]
 pub enum PageConversion {
     /// Use the single-file tool to convert it to an html-document
@@ -219,12 +454,18 @@ where
 
     string_filters
         .into_iter()
-        .map(|s| match Regex::new(&s) {
-            Ok(s) => Ok(s),
-            Err(e) => {
-                println!("Could not read config!");
-                panic!("Failed to compile regex '{}': {}", s, e);
-            }
+        .map(|s| {
+            Regex::new(&s)
+                .map_err(|e| de::Error::custom(format!("Failed to compile regex '{}': {}", s, e)))
         })
         .collect::<Result<Vec<Regex>, D::Error>>()
 }
+
+fn deserialize_optional_file_filters<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<Regex>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_file_filters(deserializer).map(Some)
+}
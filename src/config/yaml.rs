@@ -0,0 +1,74 @@
+/// Quote a string, if it would not be read back as the same string
+pub fn yaml_string(value: &str) -> String {
+    // Catches numbers in all notations (e.g. `1e3`, `0x1F`, `.inf`) and null
+    let reads_back = matches!(
+        serde_yml::from_str::<serde_yml::Value>(value),
+        Ok(serde_yml::Value::String(string)) if string == value
+    );
+    let plain = !value.is_empty()
+        && reads_back
+        && value.parse::<f64>().is_err()
+        && value
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | '(' | ')' | '/'))
+        && !value.starts_with(['-', ' '])
+        && !value.ends_with(' ')
+        && !["true", "false", "null", "yes", "no", "on", "off", "~"]
+            .contains(&value.to_lowercase().as_str());
+    if plain {
+        value.to_string()
+    } else {
+        // JSON strings are valid YAML
+        serde_json::to_string(value).unwrap_or_else(|_| value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_string_quotes_ambiguous_names() {
+        assert_eq!(yaml_string("Math 1 (WS)"), "Math 1 (WS)");
+        assert_eq!(yaml_string("linear_algebra-2.0"), "linear_algebra-2.0");
+        assert_eq!(yaml_string("/usr/bin/yt-dlp"), "/usr/bin/yt-dlp");
+        assert_eq!(
+            yaml_string("/home/me/My Tools: v2/single-file"),
+            "\"/home/me/My Tools: v2/single-file\""
+        );
+        // Numbers
+        for name in ["12", "1.5", "1e3", "0x1F", "0o17", "-3", ".5"] {
+            assert_eq!(yaml_string(name), format!("\"{}\"", name));
+        }
+        // Special floats
+        for name in [".inf", ".Inf", ".NAN", "inf", "NaN"] {
+            assert_eq!(yaml_string(name), format!("\"{}\"", name));
+        }
+        // Booleans and null (including the YAML 1.1 ones)
+        for name in ["true", "False", "yes", "NO", "on", "Off", "null", "~"] {
+            assert_eq!(yaml_string(name), format!("\"{}\"", name));
+        }
+        assert_eq!(yaml_string(""), "\"\"");
+        assert_eq!(yaml_string("Physics: Intro"), "\"Physics: Intro\"");
+        assert_eq!(yaml_string("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(yaml_string(" padded"), "\" padded\"");
+    }
+
+    #[test]
+    fn yaml_string_reads_back() {
+        for name in [
+            ".inf",
+            "0x1F",
+            "yes",
+            "Physics: Intro",
+            "#1",
+            "a # b",
+            "[x]",
+            "12",
+        ] {
+            let yaml = format!("name: {}", yaml_string(name));
+            let value: serde_yml::Value = serde_yml::from_str(&yaml).unwrap();
+            assert_eq!(value["name"].as_str(), Some(name), "{}", yaml);
+        }
+    }
+}
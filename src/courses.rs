@@ -0,0 +1,626 @@
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use indicatif::ProgressBar;
+
+use crate::api::CoreEnrolGetUsersCourses;
+use crate::config::check::{format_problems, parse_config};
+use crate::config::cli::CoursesCommand;
+use crate::config::sync_config::Course;
+use crate::config::yaml::yaml_string;
+use crate::deadlines::print_table;
+
+use super::*;
+
+pub async fn manage_courses(command: CoursesCommand) -> Result<()> {
+    match command {
+        CoursesCommand::List {
+            config_path,
+            instance,
+        } => list_courses(&config_path, instance.as_deref()).await,
+        CoursesCommand::Add {
+            course,
+            name,
+            instance,
+            config_path,
+        } => add_course(&config_path, &course, name, instance.as_deref()).await,
+        CoursesCommand::Remove {
+            course,
+            instance,
+            config_path,
+        } => remove_course(&config_path, &course, instance.as_deref()),
+    }
+}
+
+/// Print the enrolled courses and which of them are synced (`moo-dl courses list`)
+async fn list_courses(config_path: &PathBuf, instance: Option<&str>) -> Result<()> {
+    let config = read_config(config_path)?;
+    let instance_configs = match instance {
+        Some(name) => vec![instance_config(&config, Some(name))?],
+        None => config
+            .instance_configs(Path::new(""))
+            .into_iter()
+            .map(|(instance_config, _)| instance_config)
+            .collect(),
+    };
+
+    for mut instance_config in instance_configs {
+        if let Some(index) = instance_config.instance_index {
+            println!("Instance: {}", config.instances[index].name);
+        }
+        let enrolled = enrolled_courses(&mut instance_config, config_path).await?;
+
+        let mut rows: Vec<[String; 3]> = enrolled
+            .iter()
+            .map(|course| {
+                let synced = instance_config
+                    .courses
+                    .iter()
+                    .find(|synced| synced.id == course.id)
+                    .map(|synced| format!("yes (as {})", synced.name))
+                    .unwrap_or_default();
+                [course.id.to_string(), course.shortname.clone(), synced]
+            })
+            .collect();
+        // Courses, that are still in the config after the enrolment ended
+        rows.extend(
+            instance_config
+                .courses
+                .iter()
+                .filter(|synced| !enrolled.iter().any(|course| course.id == synced.id))
+                .map(|synced| {
+                    [
+                        synced.id.to_string(),
+                        "-".to_string(),
+                        format!("yes (as {}), but not enrolled", synced.name),
+                    ]
+                }),
+        );
+        print_table(["Id", "Short name", "Synced"], &rows);
+        println!();
+    }
+    Ok(())
+}
+
+/// Add an enrolled course to the config (`moo-dl courses add`)
+async fn add_course(
+    config_path: &PathBuf,
+    course: &str,
+    name: Option<String>,
+    instance: Option<&str>,
+) -> Result<()> {
+    let config = read_config(config_path)?;
+    let mut instance_config = instance_config(&config, instance)?;
+    let enrolled = enrolled_courses(&mut instance_config, config_path).await?;
+
+    let course = find_enrolled(&enrolled, course).ok_or(anyhow!(
+        "You are not enrolled in the course {} (see `moo-dl courses list`)",
+        course
+    ))?;
+    if let Some(synced) = instance_config
+        .courses
+        .iter()
+        .find(|synced| synced.id == course.id)
+    {
+        return Err(anyhow!(
+            "The course {} is already synced (as {})",
+            course.id,
+            synced.name
+        ));
+    }
+    let name = name.unwrap_or_else(|| course.shortname.clone());
+    if instance_config
+        .courses
+        .iter()
+        .any(|synced| synced.name == name)
+    {
+        return Err(anyhow!(
+            "Another course is already synced as {}: Choose another name with --name",
+            name
+        ));
+    }
+
+    let contents = fs::read_to_string(config_path).context("Failed to read config file")?;
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    insert_course(&mut lines, instance_config.instance_index, course.id, &name)?;
+
+    write_config(config_path, &contents, lines)?;
+    println!("Added the course {} (as {})", course.id, name);
+    Ok(())
+}
+
+/// Remove a course from the config (`moo-dl courses remove`)
+fn remove_course(config_path: &PathBuf, course: &str, instance: Option<&str>) -> Result<()> {
+    let config = read_config(config_path)?;
+    let instance_config = instance_config(&config, instance)?;
+    let index = synced_position(&instance_config.courses, course).ok_or(anyhow!(
+        "The course {} is not in the config (use the id or the name of the course)",
+        course
+    ))?;
+
+    let contents = fs::read_to_string(config_path).context("Failed to read config file")?;
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    delete_course(
+        &mut lines,
+        instance_config.instance_index,
+        index,
+        instance_config.courses.len(),
+    )?;
+
+    write_config(config_path, &contents, lines)?;
+    let removed = &instance_config.courses[index];
+    println!(
+        "Removed the course {} (the folder {} is kept)",
+        removed.id, removed.name
+    );
+    Ok(())
+}
+
+/// Find a synced course by its id, or else by its name (names may look like ids)
+fn synced_position(courses: &[Course], course: &str) -> Option<usize> {
+    courses
+        .iter()
+        .position(|synced| synced.id.to_string() == course)
+        .or_else(|| courses.iter().position(|synced| synced.name == course))
+}
+
+/// Append a course item to the `courses:` of the config lines
+fn insert_course(
+    lines: &mut Vec<String>,
+    instance_index: Option<usize>,
+    id: u64,
+    name: &str,
+) -> Result<()> {
+    let courses = find_courses(lines, instance_index)?;
+    let items = sequence_items(lines, &courses);
+    let indent = match items.first() {
+        Some(item) => indentation(&lines[item.start]),
+        None => courses.column + 2,
+    };
+    let insert_at = items.last().map_or(courses.line + 1, |item| item.end);
+    // An empty flow sequence (`courses: []`) becomes a block sequence
+    if items.is_empty() {
+        let line = &lines[courses.line];
+        lines[courses.line] = line[..line.find(':').unwrap_or(line.len()) + 1].to_string();
+    }
+    let item = [
+        format!("{}- id: {}", " ".repeat(indent), id),
+        format!("{}  name: {}", " ".repeat(indent), yaml_string(name)),
+    ];
+    lines.splice(insert_at..insert_at, item);
+    Ok(())
+}
+
+/// Remove the course item at `index` from the `courses:` of the config lines
+///
+/// `count` is the number of courses in the parsed config, to make sure the right item is removed
+fn delete_course(
+    lines: &mut Vec<String>,
+    instance_index: Option<usize>,
+    index: usize,
+    count: usize,
+) -> Result<()> {
+    let courses = find_courses(lines, instance_index)?;
+    let items = sequence_items(lines, &courses);
+    if items.len() != count {
+        return Err(anyhow!(
+            "Could not find the courses in the config file: Please remove the course by hand"
+        ));
+    }
+    lines.drain(items[index].clone());
+    Ok(())
+}
+
+/// Find an enrolled course by its id or short name
+pub fn find_enrolled<'a>(
+    enrolled: &'a [CoreEnrolGetUsersCourses],
+    course: &str,
+) -> Option<&'a CoreEnrolGetUsersCourses> {
+    match course.parse::<u64>() {
+        Ok(id) => enrolled.iter().find(|enrolled| enrolled.id == id),
+        Err(_) => enrolled
+            .iter()
+            .find(|enrolled| enrolled.shortname == course)
+            .or_else(|| {
+                enrolled
+                    .iter()
+                    .find(|enrolled| enrolled.shortname.eq_ignore_ascii_case(course))
+            }),
+    }
+}
+
+/// Get the config of the given instance (configs without instances don't need one)
+fn instance_config(config: &Config, instance: Option<&str>) -> Result<Config> {
+    let names = || {
+        config
+            .instances
+            .iter()
+            .map(|instance| instance.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let instance_configs = config.instance_configs(Path::new(""));
+    match instance {
+        None if config.instances.is_empty() => Ok(instance_configs.into_iter().next().unwrap().0),
+        None => Err(anyhow!(
+            "The config has several instances: Choose one with --instance ({})",
+            names()
+        )),
+        Some(_) if config.instances.is_empty() => {
+            Err(anyhow!("The config has no instances: Remove --instance"))
+        }
+        Some(name) => config
+            .instances
+            .iter()
+            .position(|instance| instance.name == name)
+            .map(|index| instance_configs.into_iter().nth(index).unwrap().0)
+            .ok_or(anyhow!(
+                "Unknown instance {} (expected one of: {})",
+                name,
+                names()
+            )),
+    }
+}
+
+async fn enrolled_courses(
+    config: &mut Config,
+    config_path: &Path,
+) -> Result<Vec<CoreEnrolGetUsersCourses>> {
+    config.renew_invalid_wstoken(config_path).await?;
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_message("Getting courses...");
+    let courses = config
+        .api_acquire_users_courses()
+        .await
+        .context("Failed getting courses")?;
+    spinner.finish_and_clear();
+    Ok(courses)
+}
+
+/// Write the edited config, if it is still valid
+fn write_config(config_path: &Path, contents: &str, lines: Vec<String>) -> Result<()> {
+    let mut edited = lines.join("\n");
+    if contents.ends_with('\n') {
+        edited.push('\n');
+    }
+    parse_config(&edited).map_err(|problems| {
+        anyhow!(
+            "Editing the config failed, please edit it by hand:\n{}",
+            format_problems(config_path, &problems)
+        )
+    })?;
+    fs::write(config_path, edited).context("Failed to write config file")?;
+    Ok(())
+}
+
+/// A key of the config file and the lines of its value
+struct KeyBlock {
+    line: usize,
+    /// Column of the key, items at this column belong to the sequence as well
+    column: usize,
+    /// End of the lines belonging to the key (exclusive)
+    end: usize,
+}
+
+/// Find the `courses:` key (of the instance at `instance_index`) in the lines of the config
+///
+/// The config is edited as text to keep the comments
+fn find_courses(lines: &[String], instance_index: Option<usize>) -> Result<KeyBlock> {
+    let not_found = || anyhow!("Could not find the courses in the config file");
+    let (range, column) = match instance_index {
+        None => (0..lines.len(), 0),
+        Some(index) => {
+            let instances =
+                find_key(lines, 0..lines.len(), 0, "instances").ok_or_else(not_found)?;
+            let items = sequence_items(lines, &instances);
+            let item = items.get(index).ok_or_else(not_found)?;
+            (item.clone(), indentation(&lines[item.start]) + 2)
+        }
+    };
+    let courses = find_key(lines, range, column, "courses").ok_or_else(not_found)?;
+
+    // Only block sequences (and the empty flow sequence) can be edited
+    let value = key_value(&lines[courses.line]);
+    if !value.is_empty() && value != "[]" {
+        return Err(anyhow!(
+            "The courses are not a block sequence: Please edit them by hand"
+        ));
+    }
+    Ok(courses)
+}
+
+/// Find a key at the given column, which may start a sequence item (`- key:`)
+fn find_key(lines: &[String], range: Range<usize>, column: usize, key: &str) -> Option<KeyBlock> {
+    let line = range.clone().find(|&index| {
+        let line = &lines[index];
+        let (key_column, text) = match line.trim_start().strip_prefix("- ") {
+            Some(text) => (indentation(line) + 2, text),
+            None => (indentation(line), line.trim_start()),
+        };
+        key_column == column
+            && text
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with(':'))
+    })?;
+
+    // Following lines belong to the key, if they are indented further or are items of a sequence at the same column
+    let mut end = line + 1;
+    for (index, line) in lines.iter().enumerate().take(range.end).skip(line + 1) {
+        if !is_content(line) {
+            continue;
+        }
+        let indent = indentation(line);
+        if indent > column || (indent == column && line.trim_start().starts_with('-')) {
+            end = index + 1;
+        } else {
+            break;
+        }
+    }
+    Some(KeyBlock { line, column, end })
+}
+
+/// The line ranges of the items of a block sequence (without the comments following an item)
+fn sequence_items(lines: &[String], key: &KeyBlock) -> Vec<Range<usize>> {
+    let Some(indent) = (key.line + 1..key.end)
+        .map(|index| &lines[index])
+        .find(|line| is_content(line))
+        .filter(|line| line.trim_start().starts_with('-'))
+        .map(|line| indentation(line))
+    else {
+        return Vec::new();
+    };
+
+    let mut items: Vec<Range<usize>> = Vec::new();
+    for (index, line) in lines.iter().enumerate().take(key.end).skip(key.line + 1) {
+        if !is_content(line) {
+            continue;
+        }
+        if indentation(line) == indent && line.trim_start().starts_with('-') {
+            items.push(index..index + 1);
+        } else if let Some(item) = items.last_mut() {
+            item.end = index + 1;
+        }
+    }
+    items
+}
+
+/// The value after the colon of a key (without a comment)
+fn key_value(line: &str) -> &str {
+    let value = line.split_once(':').map_or("", |(_, value)| value);
+    value.split(" #").next().unwrap_or_default().trim()
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Lines, that are neither empty nor comments
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn add_to_block_sequence() {
+        let mut config = lines(
+            "courses:\n  - id: 1\n    name: math\n  # comment after the last course\ndir: sync\n",
+        );
+        insert_course(&mut config, None, 2, "bio").unwrap();
+        assert_eq!(
+            config,
+            lines(
+                "courses:\n  - id: 1\n    name: math\n  - id: 2\n    name: bio\n  # comment after the last course\ndir: sync\n"
+            )
+        );
+    }
+
+    #[test]
+    fn add_to_unindented_block_sequence() {
+        let mut config = lines("courses:\n- id: 1\n  name: math\nwstoken: abc\n");
+        insert_course(&mut config, None, 2, "Physics: Intro").unwrap();
+        assert_eq!(
+            config,
+            lines(
+                "courses:\n- id: 1\n  name: math\n- id: 2\n  name: \"Physics: Intro\"\nwstoken: abc\n"
+            )
+        );
+    }
+
+    #[test]
+    fn add_to_empty_flow_sequence() {
+        let mut config = lines("wstoken: abc\ncourses: [] # nothing yet\ndir: sync\n");
+        insert_course(&mut config, None, 3, "bio").unwrap();
+        assert_eq!(
+            config,
+            lines("wstoken: abc\ncourses:\n  - id: 3\n    name: bio\ndir: sync\n")
+        );
+    }
+
+    #[test]
+    fn add_to_flow_sequence_fails() {
+        let mut config = lines("courses: [{id: 1, name: math}]\n");
+        assert!(insert_course(&mut config, None, 2, "bio").is_err());
+    }
+
+    #[test]
+    fn add_under_instance() {
+        let text = "\
+instances:
+  - name: uni
+    url: https://moodle.uni.example/
+    courses:
+      - id: 1
+        name: math
+  - name: school
+    courses: []
+    url: https://moodle.school.example/
+courses:
+  - id: 9
+    name: top
+";
+        let mut config = lines(text);
+        insert_course(&mut config, Some(0), 2, "bio").unwrap();
+        insert_course(&mut config, Some(1), 3, "art").unwrap();
+        assert_eq!(
+            config,
+            lines(
+                "\
+instances:
+  - name: uni
+    url: https://moodle.uni.example/
+    courses:
+      - id: 1
+        name: math
+      - id: 2
+        name: bio
+  - name: school
+    courses:
+      - id: 3
+        name: art
+    url: https://moodle.school.example/
+courses:
+  - id: 9
+    name: top
+"
+            )
+        );
+        // The instance does not exist
+        assert!(insert_course(&mut config, Some(2), 4, "music").is_err());
+    }
+
+    #[test]
+    fn add_to_instance_as_first_key() {
+        let mut config =
+            lines("instances:\n  - courses:\n      - id: 1\n        name: math\n    name: uni\n");
+        insert_course(&mut config, Some(0), 2, "bio").unwrap();
+        assert_eq!(
+            config,
+            lines(
+                "instances:\n  - courses:\n      - id: 1\n        name: math\n      - id: 2\n        name: bio\n    name: uni\n"
+            )
+        );
+    }
+
+    #[test]
+    fn remove_with_comments() {
+        let text = "\
+courses:
+  # Winter term
+  - id: 1
+    # keep the name short
+    name: math
+  # Summer term
+  - id: 2
+    # interleaved comment
+
+    name: bio
+  # trailing comment
+  - id: 3
+    name: art # inline comment
+# end of courses
+dir: sync
+";
+        let mut config = lines(text);
+        delete_course(&mut config, None, 1, 3).unwrap();
+        assert_eq!(
+            config,
+            lines(
+                "\
+courses:
+  # Winter term
+  - id: 1
+    # keep the name short
+    name: math
+  # Summer term
+  # trailing comment
+  - id: 3
+    name: art # inline comment
+# end of courses
+dir: sync
+"
+            )
+        );
+
+        delete_course(&mut config, None, 1, 2).unwrap();
+        assert_eq!(
+            config,
+            lines(
+                "\
+courses:
+  # Winter term
+  - id: 1
+    # keep the name short
+    name: math
+  # Summer term
+  # trailing comment
+# end of courses
+dir: sync
+"
+            )
+        );
+    }
+
+    #[test]
+    fn remove_checks_count() {
+        let mut config = lines("courses:\n  - id: 1\n    name: math\n");
+        assert!(delete_course(&mut config, None, 0, 2).is_err());
+        assert_eq!(config, lines("courses:\n  - id: 1\n    name: math\n"));
+    }
+
+    #[test]
+    fn remove_under_instance() {
+        let mut config = lines(
+            "instances:\n  - name: uni\n    courses:\n      - id: 1\n        name: math\n      - id: 2\n        name: bio\n  - name: school\n    courses: []\n",
+        );
+        delete_course(&mut config, Some(0), 0, 2).unwrap();
+        assert_eq!(
+            config,
+            lines(
+                "instances:\n  - name: uni\n    courses:\n      - id: 2\n        name: bio\n  - name: school\n    courses: []\n"
+            )
+        );
+    }
+
+    #[test]
+    fn synced_position_prefers_ids() {
+        let courses: Vec<Course> =
+            serde_yml::from_str("- id: 12\n  name: \"3\"\n- id: 3\n  name: math\n").unwrap();
+        assert_eq!(synced_position(&courses, "3"), Some(1));
+        assert_eq!(synced_position(&courses, "12"), Some(0));
+        assert_eq!(synced_position(&courses, "math"), Some(1));
+        assert_eq!(synced_position(&courses, "bio"), None);
+    }
+
+    #[test]
+    fn find_key_at_column() {
+        let config = lines("login:\n  courses: nested\ncourses:\n  - id: 1\n");
+        let key = find_key(&config, 0..config.len(), 0, "courses").unwrap();
+        assert_eq!((key.line, key.column, key.end), (2, 0, 4));
+        // Keys only match as a whole
+        assert!(find_key(&config, 0..config.len(), 0, "course").is_none());
+        let nested = find_key(&config, 0..config.len(), 2, "courses").unwrap();
+        assert_eq!((nested.line, nested.end), (1, 2));
+    }
+
+    #[test]
+    fn sequence_items_skip_trailing_comments() {
+        let config = lines(
+            "courses:\n  - id: 1\n    # inside\n    name: a\n  # after\n\n  - id: 2\n    name: b\n",
+        );
+        let key = find_key(&config, 0..config.len(), 0, "courses").unwrap();
+        assert_eq!(sequence_items(&config, &key), vec![1..4, 6..8]);
+        let empty = lines("courses: []\n");
+        let key = find_key(&empty, 0..empty.len(), 0, "courses").unwrap();
+        assert!(sequence_items(&empty, &key).is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use indicatif::ProgressBar;
+
+use crate::api::format_timestamp;
+
+use super::*;
+
+/// Print a table of everything that is due in the next `days` days
+pub async fn list_deadlines(config_path: &PathBuf, days: u64) -> Result<()> {
+    let config = read_config(config_path)?;
+
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_message("Getting deadlines...");
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let mut deadlines = Vec::new();
+    for (mut instance_config, _) in config.instance_configs(Path::new("")) {
+        instance_config.renew_invalid_wstoken(config_path).await?;
+        let instance_deadlines = instance_config
+            .api_acquire_upcoming_deadlines(days)
+            .await
+            .context("Failed getting deadlines")?;
+        // Courses of different instances may have the same name
+        let instance = instance_config
+            .instance_index
+            .map(|index| &config.instances[index].name);
+        deadlines.extend(instance_deadlines.into_iter().map(|mut deadline| {
+            if let Some(instance) = instance {
+                deadline.course = format!("{}/{}", instance, deadline.course);
+            }
+            deadline
+        }));
+    }
+    deadlines.sort_by(|a, b| (a.due, &a.course, &a.name).cmp(&(b.due, &b.course, &b.name)));
+
+    spinner.finish_and_clear();
+
+    if deadlines.is_empty() {
+        println!("Nothing due in the next {} days", days);
+        return Ok(());
+    }
+
+    let rows: Vec<[String; 5]> = deadlines
+        .into_iter()
+        .map(|deadline| {
+            [
+                format_timestamp(deadline.due),
+                deadline.course,
+                deadline.module,
+                deadline.name,
+                deadline.status,
+            ]
+        })
+        .collect();
+
+    print_table(["Due", "Course", "Type", "Name", "Status"], &rows);
+
+    Ok(())
+}
+
+/// Print rows as a table with aligned columns
+pub fn print_table<const N: usize>(header: [&str; N], rows: &[[String; N]]) {
+    // Width of each column
+    let mut widths = header.map(|column| column.chars().count());
+    for row in rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+
+    let format_row = |row: [&str; N]| {
+        row.iter()
+            .zip(widths)
+            .map(|(column, width)| format!("{:<width$}", column, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(header));
+    for row in rows {
+        println!(
+            "{}",
+            format_row(row.each_ref().map(|column| column.as_str()))
+        );
+    }
+}
@@ -72,31 +72,14 @@ impl Config {
 
         // Load cookie for moodle
         if let ChromiumState::Browser(browser) = &*browser_guard {
-            let browser_cookies = vec![
-                chromiumoxide::cdp::browser_protocol::network::CookieParam::builder()
-                    .domain(
-                        self.get_moodle_url()
-                            .host_str()
-                            .expect("This should not be possible, report this: Invalid moodle Url"),
+            if let Err(e) = self.set_browser_cookie(browser, &cookie).await {
+                self.status_bar
+                    .register_err(
+                        &e.context("Could not set browser cookie (Webbrowser unavailable)")
+                            .to_string(),
                     )
-                    .name("MoodleSession")
-                    .value(cookie.to_string())
-                    .source_port(-1)
-                    .build()
-                    .expect("This should not be possible, report this: cookieparam error"),
-            ];
-            match browser.set_cookies(browser_cookies).await {
-                Ok(_) => {}
-                Err(e) => {
-                    let err = anyhow!(e.to_string());
-                    self.status_bar
-                        .register_err(
-                            &err.context("Could not set browser cookie (Webbrowser unavailable)")
-                                .to_string(),
-                        )
-                        .await;
-                    *browser_guard = ChromiumState::Unavailable;
-                }
+                    .await;
+                *browser_guard = ChromiumState::Unavailable;
             }
         } else {
             panic!("This should not be possible, report this: browser startup error")
@@ -105,6 +88,40 @@ impl Config {
         browser_guard.downgrade()
     }
 
+    /// Set the moodle session cookie of the browser
+    async fn set_browser_cookie(&self, browser: &Browser, cookie: &str) -> Result<()> {
+        let browser_cookies = vec![
+            chromiumoxide::cdp::browser_protocol::network::CookieParam::builder()
+                .domain(
+                    self.get_moodle_url()
+                        .host_str()
+                        .expect("This should not be possible, report this: Invalid moodle Url"),
+                )
+                .name("MoodleSession")
+                .value(cookie.to_string())
+                .source_port(-1)
+                .build()
+                .expect("This should not be possible, report this: cookieparam error"),
+        ];
+        browser
+            .set_cookies(browser_cookies)
+            .await
+            .map_err(|e| anyhow!(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Replace the session cookie of an already running browser (e.g. after logging in again)
+    pub async fn update_chromium_cookie(&self, cookie: &str) {
+        let browser_guard = self.chromium.read().await;
+        if let ChromiumState::Browser(browser) = &*browser_guard
+            && let Err(e) = self.set_browser_cookie(browser, cookie).await
+        {
+            self.status_bar
+                .register_err(&e.context("Could not update browser cookie").to_string())
+                .await;
+        }
+    }
+
     /// A wrapper around `chromiumoxide::browser::close()`, that only gets only executed, if chromium is actually loaded
     /// Only run this, if you are sure that nothing is accessing the browser
     pub async fn chromium_close(&self) {
@@ -10,12 +10,30 @@ use url::Url;
 use tracing::{Span, instrument, trace};
 use tracing_indicatif::span_ext::IndicatifSpanExt;
 
+use chromiumoxide::Page;
 use web2pdf_lib::{BrowserWeb2Pdf, PageWeb2Pdf};
 
 use crate::config::sync_config::{ChromiumState, Config, PageConversion};
+use crate::login::session::{is_login_page, is_login_url};
 
 use super::super::*;
 
+enum PageSave {
+    Saved,
+    /// Saving pages is unavailable
+    Unavailable,
+    /// The session expired, moodle redirected to the login
+    LoginPage,
+}
+
+/// Check if the browser ended up on the login page
+async fn is_login_page_url(page: &Page) -> bool {
+    match page.url().await {
+        Ok(Some(url)) => Url::parse(&url).is_ok_and(|url| is_login_url(&url)),
+        _ => false,
+    }
+}
+
 impl Config {
     /// Force create a new website save
     /// # Returns
@@ -37,9 +55,34 @@ impl Config {
         // Remove old file
         let _ = fs::remove_file(&tmp_path).await;
 
+        for retry in [true, false] {
+            let cookie = self.get_cookie().await;
+            match self.save_page_once(&tmp_path, url).await? {
+                PageSave::Saved => {
+                    // Move file to destination
+                    fs::rename(tmp_path, file_path).await?;
+                    return Ok(true);
+                }
+                PageSave::Unavailable => return Ok(false),
+                PageSave::LoginPage => {
+                    let _ = fs::remove_file(&tmp_path).await;
+                    if let (true, Some(cookie)) = (retry, cookie) {
+                        self.relogin(&cookie).await?;
+                    }
+                }
+            }
+        }
+        Err(anyhow!(
+            "Got the login page instead of {}, even after logging in again",
+            url
+        ))
+    }
+
+    /// Save a page to the temporary path (a single attempt)
+    async fn save_page_once(&self, tmp_path: &Path, url: &Url) -> Result<PageSave> {
         let browser_guard = self.get_chromium().await;
         let browser = match &*browser_guard {
-            ChromiumState::Unavailable => return Ok(false),
+            ChromiumState::Unavailable => return Ok(PageSave::Unavailable),
             ChromiumState::Browser(browser) => browser,
             ChromiumState::NotStarted => {
                 return Err(anyhow!(
@@ -82,12 +125,19 @@ impl Config {
                 if !status.success() {
                     return Err(anyhow!("Single-file failed with exit code: {}", status));
                 }
+                if is_login_page(&fs::read_to_string(tmp_path).await?) {
+                    return Ok(PageSave::LoginPage);
+                }
             }
             crate::config::sync_config::PageConversion::SinglePage => {
                 let page = browser
                     .web2pdf_new_page(url.as_str())
                     .await
                     .map_err(|e| anyhow!(e.to_string()))?;
+                if is_login_page_url(&page).await {
+                    page.close().await?;
+                    return Ok(PageSave::LoginPage);
+                }
                 page.web2pdf_save_pdf_mono_standard(&tmp_path).await?;
             }
             crate::config::sync_config::PageConversion::Standard => {
@@ -95,13 +145,14 @@ impl Config {
                     .web2pdf_new_page(url.as_str())
                     .await
                     .map_err(|e| anyhow!(e.to_string()))?;
+                if is_login_page_url(&page).await {
+                    page.close().await?;
+                    return Ok(PageSave::LoginPage);
+                }
                 page.web2pdf_save_pdf_standard(&tmp_path).await?;
             }
         }
 
-        // Move file to destination
-        fs::rename(tmp_path, file_path).await?;
-
-        Ok(true)
+        Ok(PageSave::Saved)
     }
 }
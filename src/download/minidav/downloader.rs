@@ -34,6 +34,7 @@ struct PropStat {
 struct Prop {
     #[serde(rename = "quota-used-bytes")]
     quota_used_bytes: Option<u64>,
+    getcontenttype: Option<String>,
     #[serde(deserialize_with = "deserialize_http_timestamp")]
     getlastmodified: Option<u64>,
 }
@@ -58,6 +59,7 @@ impl Config {
     /// Recurses and checks every file manually, as the modification date of folders are unreliable
     pub(super) async fn download_webdav(
         config: Arc<Config>,
+        scope: &FilterScope,
         output_folder: &Path,
         url: &Url,
         share_url: Option<&Url>,
@@ -65,16 +67,16 @@ impl Config {
         pass: Option<&str>,
     ) -> Result<()> {
         trace!("Syncing webdav url: {:?}", url);
-        // Check against regex filters
-        if config
-            .check_filter(
-                &percent_decode(url.as_str().as_bytes())
-                    .decode_utf8_lossy()
-                    .into_owned(),
-            )
-            .await?
-        {
-            config.status_bar.register_skipped().await;
+        // Check against filters
+        let decoded_url = percent_decode(url.as_str().as_bytes())
+            .decode_utf8_lossy()
+            .into_owned();
+        let filter_item = FilterItem {
+            path: Some(output_folder),
+            name: &decoded_url,
+            ..Default::default()
+        };
+        if config.check_filter(&filter_item, scope).await? {
             return Ok(());
         }
 
@@ -91,6 +93,7 @@ impl Config {
                     if let Some(share_url) = share_url {
                         return download_sciebo_single(
                             config,
+                            scope,
                             output_folder,
                             share_url,
                             first.propstat.prop.getlastmodified,
@@ -116,24 +119,42 @@ impl Config {
             if response.href.ends_with('/') {
                 // Recurse for folders
                 folder_tasks.push(async {
-                    let url_segment = final_url_segment(&response.href).unwrap_or(".".to_string());
+                    let url_segment = final_url_segment(&response.href)
+                        .map(|segment| sanitize_path_component(&segment))
+                        .unwrap_or(".".to_string());
                     let path = output_folder.join(&url_segment);
                     let mut url = url.clone();
                     url.set_path(&response.href);
-                    Config::download_webdav(config.clone(), &path, &url, None, username, pass).await
+                    Config::download_webdav(
+                        config.clone(),
+                        scope,
+                        &path,
+                        &url,
+                        None,
+                        username,
+                        pass,
+                    )
+                    .await
                 });
             } else {
                 // Create file requests
                 file_tasks.push(async {
-                    let file = final_url_segment(&response.href).unwrap_or(".".to_string());
-                    // Check against regex filters
-                    if config.check_filter(&file).await? {
-                        config.status_bar.register_skipped().await;
+                    let file = final_url_segment(&response.href)
+                        .map(|segment| sanitize_path_component(&segment))
+                        .unwrap_or(".".to_string());
+                    let path = output_folder.join(&file);
+
+                    // Check against filters
+                    let filter_item = FilterItem {
+                        path: Some(&path),
+                        name: &file,
+                        mime: response.propstat.prop.getcontenttype.as_deref(),
+                        size: response.propstat.prop.quota_used_bytes,
+                    };
+                    if config.check_filter(&filter_item, scope).await? {
                         return Ok(());
                     }
 
-                    let path = output_folder.join(&file);
-
                     let mut url = url.clone();
                     url.set_path(&response.href);
                     let request = config.client.get(url).basic_auth(username, pass);
@@ -164,6 +185,7 @@ impl Config {
 /// Download a shared sciebo link as a single file (Needed for single files, as they don't get exposed via webdav)
 async fn download_sciebo_single(
     config: Arc<Config>,
+    scope: &FilterScope,
     output_folder: &Path,
     sciebo_share_url: &Url,
     timestamp: Option<u64>,
@@ -178,14 +200,19 @@ async fn download_sciebo_single(
 
     // Get filename
     let filename = get_filename_from_url_simple(&config, &sciebo_share_url).await?;
-    // Check against regex filters
-    if config.check_filter(&filename).await? {
-        config.status_bar.register_skipped().await;
+    let path = output_folder.join(sanitize_path_component(&filename));
+
+    // Check against filters
+    let filter_item = FilterItem {
+        path: Some(&path),
+        name: &filename,
+        ..Default::default()
+    };
+    if config.check_filter(&filter_item, scope).await? {
         return Ok(());
     }
 
     // Download file
-    let path = output_folder.join(&filename);
 
     let request = config.client.get(sciebo_share_url);
     config
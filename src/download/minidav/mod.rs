@@ -18,6 +18,7 @@ impl Config {
     /// Extracts Sciebo URLs from the given `search_space` and downloads them.
     pub async fn extract_sciebo_download(
         config: Arc<Config>,
+        scope: &FilterScope,
         search_space: &str,
         output_folder: PathBuf,
     ) -> Result<()> {
@@ -31,7 +32,7 @@ impl Config {
         // Iterate over every match in the search_space.
         let tasks = re
             .captures_iter(search_space)
-            .map(|cap| process_capture(config.clone(), &output_folder, cap));
+            .map(|cap| process_capture(config.clone(), scope, &output_folder, cap));
 
         // Return an error if one occured
         for res in join_all(tasks).await {
@@ -42,6 +43,7 @@ impl Config {
 }
 async fn process_capture(
     config: Arc<Config>,
+    scope: &FilterScope,
     output_folder: &Path,
     cap: Captures<'_>,
 ) -> Result<()> {
@@ -61,6 +63,7 @@ async fn process_capture(
             let pass = None;
             Config::download_webdav(
                 config,
+                scope,
                 output_folder,
                 &url,
                 Some(&parsed_url),
@@ -8,8 +8,63 @@ use std::path::Path;
 
 use tokio::fs;
 
+use crate::config::filter::{FilterItem, FilterScope, file_skip_reason, module_skip_reason};
 use crate::{Result, config::sync_config::Config};
 
+/// Maximum length of a single path component in bytes
+/// (leaves room for archive suffixes and temporary file extensions)
+pub(crate) const MAX_PATH_COMPONENT_LENGTH: usize = 200;
+
+/// Make a single path component (file or folder name) safe to use on common file systems (including network shares)
+///
+/// Replaces path separators, control characters and characters that are invalid on windows,
+/// removes trailing dots and spaces, avoids reserved names and limits the length
+pub fn sanitize_path_component(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let mut sanitized = sanitized
+        .trim_start()
+        .trim_end_matches(['.', ' '])
+        .to_string();
+
+    // Reserved device names on windows (also with an extension e.g. "con.txt")
+    let stem = sanitized
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.ends_with(|c: char| c.is_ascii_digit()));
+    if sanitized.is_empty() || reserved {
+        sanitized.insert(0, '_');
+    }
+
+    if sanitized.len() > MAX_PATH_COMPONENT_LENGTH {
+        // Keep short extensions intact
+        let (stem, extension) = match sanitized.rsplit_once('.') {
+            Some((stem, extension)) if extension.len() <= 10 && !stem.is_empty() => {
+                (stem, format!(".{}", extension))
+            }
+            _ => (sanitized.as_str(), String::new()),
+        };
+        let mut end = MAX_PATH_COMPONENT_LENGTH - extension.len();
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized = format!("{}{}", stem[..end].trim_end_matches(['.', ' ']), extension);
+    }
+
+    sanitized
+}
+
 /// Ensures that the directory specified by the given `Path` exists.
 async fn ensure_path_exists(path: &Path) -> Result<()> {
     if let Some(parent_dir) = path.parent() {
@@ -19,17 +74,43 @@ async fn ensure_path_exists(path: &Path) -> Result<()> {
 }
 
 impl Config {
-    // Check if regex filter matches passed &str
-    // # Returns
-    // true, if some regex matched
-    pub async fn check_filter(&self, str: &str) -> Result<bool> {
-        for re in &self.file_filters {
-            if re.is_match(&str) {
-                // If the filename matches one of the filters, return early.
-                self.status_bar.register_skipped().await;
-                return Ok(true);
+    /// Check the passed file (or link) of the `scope` against the filters and register it as skipped
+    /// # Returns
+    /// true, if the file should be skipped
+    pub async fn check_filter(&self, item: &FilterItem<'_>, scope: &FilterScope) -> Result<bool> {
+        let reason = match self.file_filters.iter().find(|re| re.is_match(item.name)) {
+            Some(re) => Some(format!("matched file_filters entry '{}'", re)),
+            None => file_skip_reason(&self.filters, item, scope),
+        };
+        match reason {
+            Some(reason) => {
+                self.register_filter_skip(item, &reason).await;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Check a whole module against the filters (before any of its files are checked)
+    /// # Returns
+    /// true, if the module should be skipped
+    pub async fn check_module_filter(&self, item: &FilterItem<'_>, scope: &FilterScope) -> bool {
+        match module_skip_reason(&self.filters, item, scope) {
+            Some(reason) => {
+                self.register_filter_skip(item, &reason).await;
+                true
             }
+            None => false,
         }
-        Ok(false)
+    }
+
+    async fn register_filter_skip(&self, item: &FilterItem<'_>, reason: &str) {
+        let name = match item.path {
+            Some(path) => path.to_str().unwrap_or("Unavailable"),
+            None => item.name,
+        };
+        self.status_bar
+            .register_skipped_reason(&format!("{} ({})", name, reason))
+            .await;
     }
 }
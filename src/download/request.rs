@@ -1,7 +1,11 @@
 use std::path::Path;
+use std::sync::Arc;
 
 use anyhow::anyhow;
-use reqwest::RequestBuilder;
+use reqwest::{
+    Request, RequestBuilder, Response,
+    header::{COOKIE, HeaderValue},
+};
 use tokio::{fs, fs::File, io::AsyncWriteExt};
 use tokio_stream::StreamExt;
 use tracing::{Span, instrument};
@@ -12,6 +16,7 @@ use super::*;
 use crate::{
     Result,
     config::sync_config::{Config, UpdateStrategy},
+    login::session::is_login_url,
     update::{UpdateState, timestamp::set_file_creation},
 };
 
@@ -19,8 +24,9 @@ use crate::{
 /// RequestBuilder should be created from a Client::get(url) call.
 ///
 /// Uses a temporary file for downloads to prevent data loss in case of UpdateStrategy:Update
-#[instrument(skip(file_path, request))]
+#[instrument(skip(config, file_path, request))]
 async fn force_download_file(
+    config: &Config,
     file_path: &Path,
     request: RequestBuilder,
     filesize: Option<u64>,
@@ -32,7 +38,13 @@ async fn force_download_file(
     let tmp_path = file_path.with_extension("tmp_bZpbocXJQkxt_moo-dl");
 
     // Send request and get response
-    let response = request.send().await?;
+    let request = request.build()?;
+    let retry_request = request.try_clone();
+    let mut response = config.client.execute(request).await?;
+    // Never save the login page as content
+    if is_login_url(response.url()) {
+        response = config.retry_with_new_session(retry_request).await?;
+    }
     let total_size = response
         .headers()
         .get(reqwest::header::CONTENT_LENGTH)
@@ -87,6 +99,34 @@ async fn force_download_file(
 }
 
 impl Config {
+    /// Send a request, that got the login page, again after logging in again
+    async fn retry_with_new_session(&self, request: Option<Request>) -> Result<Response> {
+        let mut request = request.ok_or(anyhow!("Session expired (got the login page)"))?;
+        // Only requests with the session cookie can be fixed by a new session
+        let expired = request
+            .headers()
+            .get(COOKIE)
+            .and_then(|cookie| cookie.to_str().ok())
+            .and_then(|cookie| cookie.strip_prefix("MoodleSession="))
+            .map(|cookie| Arc::new(cookie.to_string()))
+            .ok_or(anyhow!(
+                "Got the login page (the request has no session cookie)"
+            ))?;
+        let cookie = self.relogin(&expired).await?.ok_or(anyhow!(
+            "Session expired and the configured login did not provide a new one"
+        ))?;
+        request.headers_mut().insert(
+            COOKIE,
+            HeaderValue::from_str(&format!("MoodleSession={}", cookie))?,
+        );
+
+        let response = self.client.execute(request).await?;
+        if is_login_url(response.url()) {
+            return Err(anyhow!("Got the login page, even after logging in again"));
+        }
+        Ok(response)
+    }
+
     /// Same as `force_download_file` but only downloads if file does not exist
     /// Additionally writes the event to log
     pub async fn download_file(
@@ -97,7 +137,7 @@ impl Config {
     ) -> Result<()> {
         match UpdateStrategy::check_exists(file_path).await? {
             UpdateState::Missing => {
-                force_download_file(file_path, request, filesize).await?;
+                force_download_file(self, file_path, request, filesize).await?;
                 let message = file_path.to_str().ok_or_else(|| anyhow!("Invalid path"))?;
                 self.status_bar.register_new(message).await;
                 Ok(())
@@ -125,7 +165,7 @@ impl Config {
             .await?
         {
             UpdateState::Missing => {
-                force_download_file(file_path, request, filesize).await?;
+                force_download_file(self, file_path, request, filesize).await?;
                 set_file_creation(file_path, timestamp).await?;
 
                 let message = file_path.to_str().ok_or_else(|| anyhow!("Invalid path"))?;
@@ -133,7 +173,7 @@ impl Config {
                 Ok(())
             }
             UpdateState::OutOfDate => {
-                force_download_file(file_path, request, filesize).await?;
+                force_download_file(self, file_path, request, filesize).await?;
                 set_file_creation(file_path, timestamp).await?;
 
                 let message = file_path.to_str().ok_or_else(|| anyhow!("Invalid path"))?;
@@ -48,9 +48,19 @@ impl Config {
 
     /// Queues a video for yt-dlp
     /// Additionally checks against regex filters
-    pub async fn queue_youtube_video(&self, url: Url, output: OutputType) -> Result<()> {
-        // Check against regex filters
-        if self.check_filter(url.as_str()).await? {
+    pub async fn queue_youtube_video(
+        &self,
+        scope: &FilterScope,
+        url: Url,
+        output: OutputType,
+    ) -> Result<()> {
+        // Check against filters
+        let filter_item = FilterItem {
+            path: Some(output.path()),
+            name: url.as_str(),
+            ..Default::default()
+        };
+        if self.check_filter(&filter_item, scope).await? {
             return Ok(());
         }
 
@@ -62,6 +72,7 @@ impl Config {
     /// Extracts YouTube URLs from the given `search_space` and queues them for download.
     pub async fn queue_youtube_vidoes_extract(
         &self,
+        scope: &FilterScope,
         search_space: &str,
         output_folder: PathBuf,
     ) -> Result<()> {
@@ -85,8 +96,12 @@ impl Config {
                     }
                     tracing::trace!("Valid URL: {:?}", &parsed_url);
                     // Clone output_folder for each async call.
-                    self.queue_youtube_video(parsed_url, OutputType::Folder(output_folder.clone()))
-                        .await?;
+                    self.queue_youtube_video(
+                        scope,
+                        parsed_url,
+                        OutputType::Folder(output_folder.clone()),
+                    )
+                    .await?;
                 } else {
                 }
             }
@@ -1,29 +1,58 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, anyhow};
 use indicatif::ProgressBar;
 use tokio::fs;
 use url::Url;
 
-use dialoguer::{Input, Select};
+use dialoguer::{Confirm, Input, MultiSelect, Select};
 
 use crate::{
-    config::sync_config::{Login, rwth_url},
-    login::{graphical::login_graphical, rwth::from_rwth, user_pass::from_username_password},
+    api::CoreEnrolGetUsersCourses,
+    config::check::find_executable,
+    config::cli::{SetupArgs, SetupLogin, SetupPageConversion, SetupUpdateStrategy},
+    config::sync_config::{
+        Login, Module, PageConversion, ShibbolethFields, UpdateStrategy, rwth_url,
+    },
+    config::yaml::yaml_string,
+    courses::find_enrolled,
+    login::{
+        autologin::MOBILE_TOKENS_FILE, cas::from_cas, graphical::login_graphical,
+        headless::login_headless, oauth2::from_oauth2, rwth::from_rwth,
+        shibboleth::from_shibboleth, user_pass::from_username_password,
+    },
 };
 
 use super::*;
 
-pub async fn generate_config() -> Result<()> {
+/// Create a config file (`moo-dl setup`)
+///
+/// Asks for everything, unless a login method is given as argument
+pub async fn generate_config(args: SetupArgs) -> Result<()> {
+    // Sync looks for the mobile app tokens in the sync dir, which is next to the config
+    let mobile_tokens = args
+        .output
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(MOBILE_TOKENS_FILE);
+
     // Get Login
-    let (login, wstoken) = get_login().await?;
+    let (login, wstoken) = match args.login {
+        Some(method) => login_from_args(method, &args).await?,
+        None => get_login(&mobile_tokens).await?,
+    };
 
     let spinner = ProgressBar::new_spinner();
     spinner.set_message("Getting courses...");
 
-    let mut config = Config::default();
-    config.login = login;
-    config.wstoken = wstoken;
+    // Secret references (e.g. `env:MOODLE_PASS`) are written to the config as they are
+    let mut config = Config {
+        login: login.clone(),
+        wstoken: wstoken.clone(),
+        ..Default::default()
+    };
+    config.resolve_secrets()?;
 
     config.user_id = config
         .api_acquire_user_id()
@@ -34,11 +63,442 @@ pub async fn generate_config() -> Result<()> {
         .await
         .context("Failed getting courses")?;
 
-    spinner.finish_with_message("Successfully loaded courses!");
+    let (courses, options) = if args.login.is_some() {
+        spinner.finish_with_message("Successfully loaded courses!");
+        (
+            courses_from_args(&courses, &args)?,
+            options_from_args(&args)?,
+        )
+    } else {
+        // Semesters are usually course categories
+        let mut category_ids: Vec<u64> = courses
+            .iter()
+            .filter_map(|course| course.category)
+            .collect();
+        category_ids.sort();
+        category_ids.dedup();
+        let categories = config
+            .api_acquire_category_names(&category_ids)
+            .await
+            .unwrap_or_default();
+
+        spinner.finish_with_message("Successfully loaded courses!");
+
+        (select_courses(&courses, &categories)?, select_options()?)
+    };
+    config.login = login;
+    config.wstoken = wstoken;
+    let conf = render_config(&config, &courses, &options);
+
+    fs::write(&args.output, conf).await?;
+
+    println!("Successfully written config to: {}", args.output.display());
+    println!("You may now modify it to you liking!");
+
+    Ok(())
+}
+
+/// Modules offered by the setup (with a comment for the config)
+const SETUP_MODULES: [(Module, Option<&str>); 12] = [
+    (Module::Resource, None),
+    (Module::Folder, None),
+    (Module::Pdfannotator, None),
+    (Module::Assign, Some("Assignments:")),
+    (Module::Label, None),
+    (Module::Url, None),
+    (Module::Page, None),
+    (Module::Quiz, None),
+    (Module::Glossary, None),
+    (Module::Vpl, None),
+    (
+        Module::Lti,
+        Some("Currently Lti is equivalent to opencast (which requires youtube to be enabled)"),
+    ),
+    (Module::Grouptool, None),
+];
+
+/// Modules, that are not synced by default
+const OPTIONAL_MODULES: [Module; 2] = [Module::Quiz, Module::Vpl];
+
+/// Everything the setup asks for (besides the login and courses)
+pub struct SetupOptions {
+    pub modules: Vec<Module>,
+    pub update_strategy: UpdateStrategy,
+    /// Path of yt-dlp, if youtube videos are downloaded
+    pub youtube: Option<PathBuf>,
+    pub page_conversion: PageConversion,
+    pub chrome_executable: Option<PathBuf>,
+}
+
+/// Let the user choose the courses to sync and the names of their folders
+fn select_courses(
+    courses: &[CoreEnrolGetUsersCourses],
+    categories: &HashMap<u64, String>,
+) -> Result<Vec<(u64, String)>> {
+    let items: Vec<String> = courses
+        .iter()
+        .map(|course| {
+            let category = course
+                .category
+                .and_then(|category| categories.get(&category))
+                .map(|category| format!(" - {}", category))
+                .unwrap_or_default();
+            format!("{} ({}){}", course.fullname, course.shortname, category)
+        })
+        .collect();
+    let selection = MultiSelect::new()
+        .with_prompt("Choose the courses to sync (space to toggle, enter to confirm)")
+        .items(&items)
+        .defaults(&vec![true; items.len()])
+        .interact()?;
+
+    let rename = !selection.is_empty()
+        && Confirm::new()
+            .with_prompt("Rename the course folders? (they are named by the short names)")
+            .default(false)
+            .interact()?;
+    selection
+        .into_iter()
+        .map(|index| {
+            let course = &courses[index];
+            let name = if rename {
+                Input::new()
+                    .with_prompt(format!("Folder name of {}", course.fullname))
+                    .default(course.shortname.clone())
+                    .interact_text()?
+            } else {
+                course.shortname.clone()
+            };
+            Ok((course.id, name))
+        })
+        .collect()
+}
+
+/// Ask for the modules, update strategy, youtube and page conversion
+///
+/// The paths of yt-dlp, single-file and chromium are prefilled, if they are found
+fn select_options() -> Result<SetupOptions> {
+    let module_names: Vec<String> = SETUP_MODULES
+        .iter()
+        .map(|(module, _)| format!("{:?}", module))
+        .collect();
+    let defaults: Vec<bool> = SETUP_MODULES
+        .iter()
+        .map(|(module, _)| !OPTIONAL_MODULES.contains(module))
+        .collect();
+    let modules = MultiSelect::new()
+        .with_prompt("Choose the modules to sync")
+        .items(&module_names)
+        .defaults(&defaults)
+        .interact()?
+        .into_iter()
+        .map(|index| SETUP_MODULES[index].0.clone())
+        .collect();
+
+    let strategies = [
+        UpdateStrategy::None,
+        UpdateStrategy::Update,
+        UpdateStrategy::Archive,
+    ];
+    let strategy = Select::new()
+        .with_prompt("What should happen to files, that changed on moodle?")
+        .items([
+            "None - Keep the downloaded version",
+            "Update - Replace it with the new version",
+            "Archive - Replace it and keep the old version",
+        ])
+        .default(2)
+        .interact()?;
+    let update_strategy = strategies[strategy].clone();
+
+    let yt_dlp = find_executable(Path::new("yt-dlp"));
+    let youtube = if Confirm::new()
+        .with_prompt("Download youtube videos (and opencast lectures) with yt-dlp?")
+        .default(yt_dlp.is_some())
+        .interact()?
+    {
+        Some(ask_path("Path of yt-dlp", yt_dlp, "yt-dlp")?)
+    } else {
+        None
+    };
+
+    let single_file = find_executable(Path::new("single-file"));
+    let page_conversion = Select::new()
+        .with_prompt("How should pages be saved?")
+        .items([
+            "Standard - As pdf",
+            "SinglePage - As pdf with a single page",
+            "SingleFile - As html document (needs single-file)",
+        ])
+        .default(if single_file.is_some() { 2 } else { 0 })
+        .interact()?;
+    let page_conversion = match page_conversion {
+        0 => PageConversion::Standard,
+        1 => PageConversion::SinglePage,
+        _ => {
+            PageConversion::SingleFile(ask_path("Path of single-file", single_file, "single-file")?)
+        }
+    };
+
+    let chrome_executable: String = Input::new()
+        .with_prompt("Path of chromium (leave empty for auto detection)")
+        .with_initial_text(
+            find_chromium()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+        )
+        .allow_empty(true)
+        .interact_text()?;
+    let chrome_executable =
+        Some(PathBuf::from(chrome_executable.trim())).filter(|path| !path.as_os_str().is_empty());
+
+    Ok(SetupOptions {
+        modules,
+        update_strategy,
+        youtube,
+        page_conversion,
+        chrome_executable,
+    })
+}
+
+/// Create the login from the arguments and log in
+///
+/// The secrets are written as `env:` references, unless `--write-secrets` is given
+async fn login_from_args(method: SetupLogin, args: &SetupArgs) -> Result<(Login, String)> {
+    let url = || -> Result<Url> {
+        let url = args
+            .url
+            .as_deref()
+            .ok_or(anyhow!("The login needs --url"))?;
+        Url::parse(url).map_err(|e| anyhow!("Error parsing --url: {}", e))
+    };
+    let username = || {
+        args.username
+            .clone()
+            .ok_or(anyhow!("The login needs --username"))
+    };
+    // Secrets are only passed via environment variables, as arguments are visible to other users.
+    // The config references the variables instead of containing the secrets.
+    let secret = |flag: &str, variable: &Option<String>| -> Result<String> {
+        let variable = variable
+            .as_deref()
+            .ok_or(anyhow!("The login needs --{}", flag))?;
+        Ok(format!("env:{}", variable))
+    };
+    let optional_secret = |variable: &Option<String>| {
+        variable
+            .as_deref()
+            .map(|variable| format!("env:{}", variable))
+    };
+
+    let login = match method {
+        SetupLogin::ApiOnly => {
+            let wstoken = secret("wstoken-env", &args.wstoken_env)?;
+            let private_token = optional_secret(&args.private_token_env);
+            let login = Login::ApiOnly {
+                url: url()?,
+                private_token,
+            };
+            return written_secrets(login, wstoken, args.write_secrets);
+        }
+        SetupLogin::UserPass => Login::UserPass {
+            url: url()?,
+            username: username()?,
+            password: secret("password-env", &args.password_env)?,
+        },
+        SetupLogin::Rwth => Login::Rwth {
+            url: rwth_url(),
+            username: username()?,
+            password: secret("password-env", &args.password_env)?,
+            totp: args.totp.clone().ok_or(anyhow!("The login needs --totp"))?,
+            totp_secret: secret("totp-secret-env", &args.totp_secret_env)?,
+        },
+        SetupLogin::Shibboleth => Login::Shibboleth {
+            url: url()?,
+            username: username()?,
+            password: secret("password-env", &args.password_env)?,
+            totp_secret: optional_secret(&args.totp_secret_env),
+            fields: ShibbolethFields::default(),
+        },
+        SetupLogin::Cas => Login::Cas {
+            url: url()?,
+            username: username()?,
+            password: secret("password-env", &args.password_env)?,
+        },
+    };
+
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_message("Logging in...");
+    let mut config = Config {
+        login: login.clone(),
+        ..Default::default()
+    };
+    config.resolve_secrets()?;
+    let wstoken = config
+        .fresh_wstoken()
+        .await
+        .context("Login failed")?
+        .ok_or(anyhow!("The login did not provide a wstoken"))?;
+    spinner.finish_with_message("Successfully logged in!");
+
+    written_secrets(login, wstoken, args.write_secrets)
+}
+
+/// The login and wstoken as written to the config (with the resolved secrets, if `resolve` is set)
+fn written_secrets(login: Login, wstoken: String, resolve: bool) -> Result<(Login, String)> {
+    if !resolve {
+        return Ok((login, wstoken));
+    }
+    let mut config = Config {
+        login,
+        wstoken,
+        ..Default::default()
+    };
+    config.resolve_secrets()?;
+    Ok((config.login, config.wstoken))
+}
+
+/// The courses given by --all-courses or --courses
+fn courses_from_args(
+    courses: &[CoreEnrolGetUsersCourses],
+    args: &SetupArgs,
+) -> Result<Vec<(u64, String)>> {
+    if args.all_courses {
+        return Ok(courses
+            .iter()
+            .map(|course| (course.id, course.shortname.clone()))
+            .collect());
+    }
+    if args.courses.is_empty() {
+        return Err(anyhow!(
+            "Choose the courses with --all-courses or --courses"
+        ));
+    }
+    args.courses
+        .iter()
+        .map(|course| {
+            find_enrolled(courses, course)
+                .map(|course| (course.id, course.shortname.clone()))
+                .ok_or(anyhow!("You are not enrolled in the course {}", course))
+        })
+        .collect()
+}
 
+/// The options given as arguments, the defaults match the ones of `select_options`
+fn options_from_args(args: &SetupArgs) -> Result<SetupOptions> {
+    let modules = if args.modules.is_empty() {
+        SETUP_MODULES
+            .iter()
+            .map(|(module, _)| module.clone())
+            .filter(|module| !OPTIONAL_MODULES.contains(module))
+            .collect()
+    } else {
+        args.modules
+            .iter()
+            .map(|name| {
+                SETUP_MODULES
+                    .iter()
+                    .map(|(module, _)| module)
+                    .find(|module| format!("{:?}", module).eq_ignore_ascii_case(name))
+                    .cloned()
+                    .ok_or(anyhow!(
+                        "Unknown module {} (expected one of: {})",
+                        name,
+                        SETUP_MODULES
+                            .iter()
+                            .map(|(module, _)| format!("{:?}", module))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let update_strategy = match args.update_strategy {
+        SetupUpdateStrategy::None => UpdateStrategy::None,
+        SetupUpdateStrategy::Update => UpdateStrategy::Update,
+        SetupUpdateStrategy::Archive => UpdateStrategy::Archive,
+    };
+
+    let youtube = if args.no_youtube {
+        None
+    } else {
+        args.youtube
+            .clone()
+            .or_else(|| find_executable(Path::new("yt-dlp")))
+    };
+
+    let single_file = args
+        .single_file
+        .clone()
+        .or_else(|| find_executable(Path::new("single-file")));
+    let page_conversion = match (args.page_conversion, single_file) {
+        (Some(SetupPageConversion::Standard), _) => PageConversion::Standard,
+        (Some(SetupPageConversion::SinglePage), _) => PageConversion::SinglePage,
+        (Some(SetupPageConversion::SingleFile), None) => {
+            return Err(anyhow!(
+                "single-file was not found on PATH: Set its path with --single-file"
+            ));
+        }
+        (_, Some(path)) => PageConversion::SingleFile(path),
+        (None, None) => PageConversion::Standard,
+    };
+
+    Ok(SetupOptions {
+        modules,
+        update_strategy,
+        youtube,
+        page_conversion,
+        chrome_executable: args.chrome_executable.clone().or_else(find_chromium),
+    })
+}
+
+/// Ask for the path of an executable (prefilled with the found one)
+fn ask_path(prompt: &str, found: Option<PathBuf>, name: &str) -> Result<PathBuf> {
+    if found.is_none() {
+        println!("{} was not found on PATH", name);
+    }
+    let path: String = Input::new()
+        .with_prompt(prompt)
+        .default(
+            found
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| name.to_string()),
+        )
+        .interact_text()?;
+    Ok(PathBuf::from(path))
+}
+
+/// Find a chromium based browser on PATH
+fn find_chromium() -> Option<PathBuf> {
+    [
+        "chromium",
+        "chromium-browser",
+        "google-chrome",
+        "google-chrome-stable",
+        "chrome",
+    ]
+    .into_iter()
+    .find_map(|name| find_executable(Path::new(name)))
+}
+
+/// Comment out every line of a config snippet
+fn comment_out(snippet: &str) -> String {
+    snippet
+        .lines()
+        .map(|line| format!("# {}\n", line))
+        .collect()
+}
+
+/// Create the config file contents
+fn render_config(config: &Config, courses: &[(u64, String)], options: &SetupOptions) -> String {
     // Config head
     let mut conf = format!(
-        r#"# Token for API
+        r#"# Secrets (wstoken, password, totp_secret, private_token and cookie) may be given directly or as a reference:
+#   env:MOODLE_TOKEN, file:/run/secrets/moodle_token or command:pass show moodle/token
+#   The system keyring is only supported via command:, e.g. command:secret-tool lookup service moodle (linux)
+#   or command:security find-generic-password -w -s moodle (macOS)
+# Token for API
 wstoken: {}
 # Your moodle user id (needed for some operations)
 user_id: {}
@@ -46,27 +506,54 @@ user_id: {}
 # Login parameters
 login:
 "#,
-        config.wstoken, config.user_id
+        yaml_string(&config.wstoken),
+        config.user_id
     );
     // Add Login parameters
     conf.push_str(&match &config.login {
-        Login::ApiOnly { url } => {
-            format!(
+        Login::ApiOnly { url, private_token } => {
+            let mut login = format!(
                 r#"  type: ApiOnly
   url: {}
+"#,
+                yaml_string(url.as_str())
+            );
+            if let Some(private_token) = private_token {
+                login.push_str(&format!(
+                    "  private_token: {}\n",
+                    yaml_string(private_token)
+                ));
+            }
+            login.push('\n');
+            login
+        }
+        Login::Raw { url, cookie } => {
+            format!(
+                r#"  type: Raw
+  url: {}
+  cookie: {}
 
 "#,
-                url
+                yaml_string(url.as_str()),
+                yaml_string(cookie)
             )
         }
-        Login::Raw { url: _, cookie: _ } => panic!("Raw login config generation not supported!"),
         Login::Graphical { url } => {
             format!(
                 r#"  type: Graphical
   url: {}
 
 "#,
-                url
+                yaml_string(url.as_str())
+            )
+        }
+        Login::Headless { url } => {
+            format!(
+                r#"  type: Headless
+  url: {}
+
+"#,
+                yaml_string(url.as_str())
             )
         }
         Login::UserPass {
@@ -81,7 +568,9 @@ login:
   password: {}
 
 "#,
-                url, username, password
+                yaml_string(url.as_str()),
+                yaml_string(username),
+                yaml_string(password)
             )
         }
         Login::Rwth {
@@ -99,9 +588,75 @@ login:
   totp_secret: {}
 
 "#,
-                username, password, totp, totp_secret
+                yaml_string(username),
+                yaml_string(password),
+                yaml_string(totp),
+                yaml_string(totp_secret)
             )
         }
+        Login::Cas {
+            url,
+            username,
+            password,
+        } => {
+            format!(
+                r#"  type: Cas
+  url: {}
+  username: {}
+  password: {}
+
+"#,
+                yaml_string(url.as_str()),
+                yaml_string(username),
+                yaml_string(password)
+            )
+        }
+        Login::Shibboleth {
+            url,
+            username,
+            password,
+            totp_secret,
+            fields,
+        } => {
+            let mut login = format!(
+                r#"  type: Shibboleth
+  url: {}
+  username: {}
+  password: {}
+"#,
+                yaml_string(url.as_str()),
+                yaml_string(username),
+                yaml_string(password)
+            );
+            if let Some(totp_secret) = totp_secret {
+                login.push_str(&format!("  totp_secret: {}\n", yaml_string(totp_secret)));
+            }
+            login.push_str(&format!(
+                r#"  fields:
+    username: {}
+    password: {}
+    totp: {}
+
+"#,
+                yaml_string(&fields.username),
+                yaml_string(&fields.password),
+                yaml_string(&fields.totp)
+            ));
+            login
+        }
+        Login::OAuth2 { url, issuer } => {
+            let mut login = format!(
+                r#"  type: OAuth2
+  url: {}
+"#,
+                yaml_string(url.as_str())
+            );
+            if let Some(issuer) = issuer {
+                login.push_str(&format!("  issuer: {}\n", yaml_string(issuer)));
+            }
+            login.push('\n');
+            login
+        }
     });
 
     // Add Courses
@@ -110,65 +665,129 @@ login:
 courses:
 "#,
     );
-    for course in courses {
+    for (id, name) in courses {
         conf.push_str(&format!(
             "  - id: {}\n    name: {}\n",
-            course.id, course.shortname
+            id,
+            yaml_string(name)
         ));
     }
 
     conf.push_str(
         r#"
+# Several moodle instances (or accounts) may be synced in one run:
+# Move wstoken, user_id, login and courses into a list of "instances" (see example-config.yml)
+# Each instance is synced into a subdirectory of its name
 
 ### Additional Options for fine tuning
 
 # Modules to sync
-modules:
-  - Resource
-  - Folder
-  - Pdfannotator
-  # Assignments:
-  - Assign
-  - Label
-  - Url
-  - Page
-  #- Quiz
-  - Glossary
-  #- Vpl
-  # Currently Lti is equivalent to opencast (which requires youtube to be enabled)
-  - Lti
-  - Grouptool
+modules:"#,
+    );
+    // Without any module, the list would be empty (null)
+    conf.push_str(if options.modules.is_empty() {
+        " []\n"
+    } else {
+        "\n"
+    });
+    for (module, comment) in SETUP_MODULES {
+        if let Some(comment) = comment {
+            conf.push_str(&format!("  # {}\n", comment));
+        }
+        let prefix = if options.modules.contains(&module) {
+            ""
+        } else {
+            "#"
+        };
+        conf.push_str(&format!("  {}- {:?}\n", prefix, module));
+    }
 
+    conf.push_str(&format!(
+        r#"
 # Enables saving grades
+# (as grades.csv / grades.json per course and a grades_overview.csv in the sync dir)
 grades: true
 
+# Enables saving assignment due dates and upcoming events as deadlines.ics in the sync dir (default: true)
+deadlines: true
+
 # One of keep "None / Update / Archive"
-update_strategy: Archive
+update_strategy: {:?}
 
 # Optionally set path of chrome executable (instead of autodetect
 # (may be removed)
-#chrome_executable: /usr/bin/chromium-browser
+{}chrome_executable: {}
 
 # Enables downloading of linked sciebo files/folders
 sciebo: true
 
 # Enables downloading youtube videos (may be removed)
-youtube:
-  path: yt-dlp
+"#,
+        options.update_strategy,
+        if options.chrome_executable.is_some() {
+            ""
+        } else {
+            "#"
+        },
+        yaml_string(
+            &options
+                .chrome_executable
+                .as_deref()
+                .unwrap_or(Path::new("/usr/bin/chromium-browser"))
+                .display()
+                .to_string()
+        )
+    ));
+    let youtube = format!(
+        r#"youtube:
+  path: {}
   params:
     - -N
     - 4
   parallel_downloads: 3
+"#,
+        yaml_string(
+            &options
+                .youtube
+                .as_deref()
+                .unwrap_or(Path::new("yt-dlp"))
+                .display()
+                .to_string()
+        )
+    );
+    if options.youtube.is_some() {
+        conf.push_str(&youtube);
+    } else {
+        conf.push_str(&comment_out(&youtube));
+    }
 
+    let page_conversion = match &options.page_conversion {
+        PageConversion::SingleFile(path) => {
+            format!(
+                "  type: SingleFile\n  path: {}\n",
+                yaml_string(&path.display().to_string())
+            )
+        }
+        PageConversion::SinglePage => "  type: SinglePage\n".to_string(),
+        PageConversion::Standard => "  type: Standard\n".to_string(),
+    };
+    conf.push_str(&format!(
+        r#"
 # How webpages should be saved (only one)
+# SingleFile: Use the single-file tool to convert it to an html-document (needs the path of single-file)
+# SinglePage: Store entire file as pdf with a single page
+# Standard: Standard chrome pdf
 page_conversion:
-  # # Use the sing-file tool to convert it to an html-document
-  # type: SingleFile
-  # path: /path/to/single-file
-  # # Store entire file as pdf with a single page
-  # type: SinglePage
-  # Standard chrome pdf
-  type: Standard
+{}"#,
+        page_conversion
+    ));
+
+    conf.push_str(
+        r#"
+# Optional: Folders of sections and modules inside of a course (files keep their names inside the module folder)
+# Placeholders: {section}, {section_index} (e.g. {section_index:02} for 01, 02, ...), {module}
+# May be overwritten per course by adding path_template to the course
+# path_template: "{section_index:02} - {section}/{module}"
 
 # Optional: Dir to sync to (may be removed)
 # dir: ./cool/path
@@ -176,35 +795,43 @@ page_conversion:
 # Optional: Dir to sync to (may be removed)
 log_file: moo-dl.log
 
-# Optional: Regex to filter out files
-# Warning: These only get applied to:
-#   1. filenames as presented by the moodle api
-#   2. youtube and opencast urls
-#   3. sciebo downloads
-# (to filter other files please remove the corresponding modules directly)
+# Optional: Regex to filter out files (matched against file names, video urls and sciebo urls)
 file_filters:
   # - reg1
   # - reg2
+
+# Optional: Include and exclude rules, checked in order before anything gets downloaded
+# The first matching rule decides, if no rule matches the file gets downloaded
+# (unless there are Include rules, then only included files get downloaded)
+# A rule matches, if all of its conditions match:
+#   path / filename: regex, extension: list, mime: list (e.g. video/*),
+#   min_size / max_size: bytes or with unit (e.g. 500MB, 1GiB), module: list, course: list of ids
+# Skipped files get listed with the matching rule in the log
+filters:
+  # - action: Exclude
+  #   extension: [mp4, mkv]
+  #   min_size: 500MB
+  # - action: Exclude
+  #   module: [Page, Label]
+  #   course: [12347]
 "#,
     );
 
-    let config_path = ".moo-dl-config.yml";
-    fs::write(config_path, conf).await?;
-
-    println!("Successfully written config to: {}", config_path);
-    println!("You may now modify it to you liking!");
-
-    Ok(())
+    conf
 }
 
 // Return Login and wstoken
-async fn get_login() -> Result<(Login, String)> {
+async fn get_login(mobile_tokens: &Path) -> Result<(Login, String)> {
     // Define login options.
     let items = vec![
         "API Only - Provide API capabilities only (needs a wstoken, if you do not know what this is use another option)",
         "Graphical - Use a graphical login interface (opens a webbrowser for login)",
         "User/Pass - Use username and password authentication",
         "RWTH - Use the RWTH SSO with TOTP",
+        "Shibboleth - Use the SSO of your university (SAML via a Shibboleth identity provider)",
+        "OAuth2 - Use a login provider of moodle (e.g. Microsoft, Google, Keycloak), works without a local browser",
+        "CAS - Use the CAS login of your university (Apereo CAS)",
+        "Headless - Log in on another device (e.g. when running on a server via ssh)",
     ];
 
     // Let the user select a login method.
@@ -226,8 +853,13 @@ async fn get_login() -> Result<(Login, String)> {
             let wstoken: String = Input::new()
                 .with_prompt("Enter your wstoken")
                 .interact_text()?;
+            let private_token: String = Input::new()
+                .with_prompt("Enter the private token of the mobile app (allows full functionality, leave empty if unknown)")
+                .allow_empty(true)
+                .interact_text()?;
+            let private_token = Some(private_token).filter(|token| !token.trim().is_empty());
 
-            (Login::ApiOnly { url }, wstoken)
+            (Login::ApiOnly { url, private_token }, wstoken)
         }
         1 => {
             // Graphical
@@ -343,8 +975,233 @@ async fn get_login() -> Result<(Login, String)> {
                 wstoken,
             )
         }
+        4 => {
+            // Shibboleth
+            let url_str: String = Input::new()
+                .with_prompt("Enter your moodle url (e.g. https://moodle.example.com)")
+                .interact_text()?;
+
+            let url = Url::parse(&url_str)
+                .map_err(|e| anyhow!("Error parsing URL for Shibboleth: {}", e))?;
+
+            let username: String = Input::new()
+                .with_prompt("Enter your username")
+                .interact_text()?;
+            let password: String = Input::new()
+                .with_prompt("Enter your password")
+                .interact_text()?;
+            let totp_secret: String = Input::new()
+                .with_prompt("Enter your TOTP Authenticator key (leave empty, if your university does not ask for one)")
+                .allow_empty(true)
+                .interact_text()?;
+            let totp_secret = Some(totp_secret).filter(|secret| !secret.trim().is_empty());
+
+            let defaults = ShibbolethFields::default();
+            let fields = ShibbolethFields {
+                username: Input::new()
+                    .with_prompt("Name of the username field of the login form")
+                    .default(defaults.username)
+                    .interact_text()?,
+                password: Input::new()
+                    .with_prompt("Name of the password field of the login form")
+                    .default(defaults.password)
+                    .interact_text()?,
+                totp: Input::new()
+                    .with_prompt("Name of the TOTP field of the login form")
+                    .default(defaults.totp)
+                    .interact_text()?,
+            };
+
+            let spinner = ProgressBar::new_spinner();
+            spinner.set_message("Logging in...");
+
+            let login_params = from_shibboleth(
+                &url,
+                &username,
+                &password,
+                totp_secret.as_deref(),
+                &fields,
+                true,
+            )
+            .await
+            .context("Shibboleth Login failed")?;
+            let wstoken = login_params
+                .wstoken
+                .expect("Could not get wstoken from login");
+
+            spinner.finish_with_message("Successfully logged in!");
+
+            (
+                Login::Shibboleth {
+                    url,
+                    username,
+                    password,
+                    totp_secret,
+                    fields,
+                },
+                wstoken,
+            )
+        }
+        5 => {
+            // OAuth2
+            let url_str: String = Input::new()
+                .with_prompt("Enter your moodle url (e.g. https://moodle.example.com)")
+                .interact_text()?;
+
+            let url =
+                Url::parse(&url_str).map_err(|e| anyhow!("Error parsing URL for OAuth2: {}", e))?;
+
+            let issuer: String = Input::new()
+                .with_prompt(
+                    "Enter the name of the login provider (leave empty, if moodle only offers one)",
+                )
+                .allow_empty(true)
+                .interact_text()?;
+            let issuer = Some(issuer).filter(|issuer| !issuer.trim().is_empty());
+
+            let login_params = from_oauth2(&url, issuer.as_deref(), Some(mobile_tokens), true)
+                .await
+                .context("OAuth2 Login failed")?;
+            let wstoken = login_params
+                .wstoken
+                .expect("Could not get wstoken from login");
+
+            println!("Successfully logged in!");
+
+            (Login::OAuth2 { url, issuer }, wstoken)
+        }
+        6 => {
+            // CAS
+            let url_str: String = Input::new()
+                .with_prompt("Enter your moodle url (e.g. https://moodle.example.com)")
+                .interact_text()?;
+
+            let url =
+                Url::parse(&url_str).map_err(|e| anyhow!("Error parsing URL for CAS: {}", e))?;
+
+            let username: String = Input::new()
+                .with_prompt("Enter your username")
+                .interact_text()?;
+            let password: String = Input::new()
+                .with_prompt("Enter your password")
+                .interact_text()?;
+
+            let spinner = ProgressBar::new_spinner();
+            spinner.set_message("Logging in...");
+
+            let login_params = from_cas(&url, &username, &password, true)
+                .await
+                .context("CAS Login failed")?;
+            let wstoken = login_params
+                .wstoken
+                .expect("Could not get wstoken from login");
+
+            spinner.finish_with_message("Successfully logged in!");
+
+            (
+                Login::Cas {
+                    url,
+                    username,
+                    password,
+                },
+                wstoken,
+            )
+        }
+        7 => {
+            // Headless
+            let url_str: String = Input::new()
+                .with_prompt("Enter your moodle url (e.g. https://moodle.example.com)")
+                .interact_text()?;
+
+            let url = Url::parse(&url_str)
+                .map_err(|e| anyhow!("Error parsing URL for Headless: {}", e))?;
+
+            let login_params = login_headless(&url, Some(mobile_tokens), true)
+                .await
+                .context("Headless Login failed")?;
+            let wstoken = login_params
+                .wstoken
+                .expect("Could not get wstoken from login");
+
+            println!("Successfully logged in!");
+
+            (Login::Headless { url }, wstoken)
+        }
         _ => return Err(anyhow!("Invalid selection")),
     };
 
     Ok((login, wstoken))
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Setup {
+        #[clap(flatten)]
+        args: SetupArgs,
+    }
+
+    fn render(login: Login, wstoken: String) -> String {
+        let config = Config {
+            login,
+            wstoken,
+            user_id: 1,
+            ..Default::default()
+        };
+        let options = SetupOptions {
+            modules: vec![Module::Resource, Module::Folder],
+            update_strategy: UpdateStrategy::Archive,
+            youtube: None,
+            page_conversion: PageConversion::Standard,
+            chrome_executable: None,
+        };
+        render_config(&config, &[(1, "math".to_string())], &options)
+    }
+
+    #[tokio::test]
+    async fn setup_without_prompts_matches_the_prompts() {
+        let url = Url::parse("https://moodle.example.com").unwrap();
+        let args = |extra: &[&str]| {
+            let base = [
+                "setup",
+                "--login",
+                "api-only",
+                "--url",
+                url.as_str(),
+                "--wstoken-env",
+                "PATH",
+                "--private-token-env",
+                "HOME",
+            ];
+            Setup::parse_from(base.iter().chain(extra)).args
+        };
+        let api_only = |wstoken: String, private_token: String| {
+            let login = Login::ApiOnly {
+                url: url.clone(),
+                private_token: Some(private_token),
+            };
+            render(login, wstoken)
+        };
+
+        // The prompts write the secrets themselves
+        let prompted = api_only(
+            std::env::var("PATH").unwrap(),
+            std::env::var("HOME").unwrap(),
+        );
+        let (login, wstoken) = login_from_args(SetupLogin::ApiOnly, &args(&["--write-secrets"]))
+            .await
+            .unwrap();
+        assert_eq!(render(login, wstoken), prompted);
+
+        // By default the variables are referenced instead
+        let referenced = api_only("env:PATH".to_string(), "env:HOME".to_string());
+        let (login, wstoken) = login_from_args(SetupLogin::ApiOnly, &args(&[]))
+            .await
+            .unwrap();
+        assert_eq!(render(login, wstoken), referenced);
+    }
+}
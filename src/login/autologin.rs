@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use reqwest::{Client, cookie::Jar};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+use tracing::{debug, warn};
+
+use super::session::{is_login_url, write_private_file};
+use super::*;
+
+/// File in the sync dir, that stores the mobile app tokens (of the OAuth2 and headless logins)
+pub const MOBILE_TOKENS_FILE: &str = ".moo-dl.mobile-tokens.json";
+
+/// The tokens of the mobile app, they allow renewing the session without signing in again
+#[derive(Debug, Deserialize, Serialize)]
+struct MobileTokens {
+    url: Url,
+    wstoken: String,
+    privatetoken: String,
+}
+
+/// Renew the session using the stored mobile app tokens
+/// # Returns
+/// None, if there are no stored tokens or moodle refused them
+pub async fn renew_session(
+    instance_url: &Url,
+    tokens_path: Option<&Path>,
+    wstoken_request: bool,
+) -> Option<LoginParams> {
+    let tokens = load_mobile_tokens(tokens_path?, instance_url).await?;
+    match session_from_private_token(instance_url, &tokens.wstoken, &tokens.privatetoken).await {
+        Ok(cookie) => Some(LoginParams {
+            cookie,
+            wstoken: wstoken_request.then_some(tokens.wstoken),
+        }),
+        Err(e) => {
+            warn!("Could not renew the session using the stored tokens: {}", e);
+            None
+        }
+    }
+}
+
+/// The stored private token, if it belongs to the wstoken
+pub async fn stored_private_token(
+    tokens_path: Option<&Path>,
+    instance_url: &Url,
+    wstoken: &str,
+) -> Option<String> {
+    load_mobile_tokens(tokens_path?, instance_url)
+        .await
+        .filter(|tokens| tokens.wstoken == wstoken)
+        .map(|tokens| tokens.privatetoken)
+}
+
+async fn load_mobile_tokens(tokens_path: &Path, instance_url: &Url) -> Option<MobileTokens> {
+    let contents = fs::read_to_string(tokens_path).await.ok()?;
+    let tokens: MobileTokens = match serde_json::from_str(&contents) {
+        Ok(tokens) => tokens,
+        Err(e) => {
+            warn!("Ignoring invalid mobile tokens file: {}", e);
+            return None;
+        }
+    };
+    register_secret(&tokens.wstoken);
+    register_secret(&tokens.privatetoken);
+    Some(tokens).filter(|tokens| &tokens.url == instance_url)
+}
+
+/// Store the mobile app tokens for renewing the session in the next runs
+pub async fn save_mobile_tokens(
+    tokens_path: &Path,
+    instance_url: &Url,
+    wstoken: &str,
+    privatetoken: &str,
+) -> Result<()> {
+    let tokens = MobileTokens {
+        url: instance_url.clone(),
+        wstoken: wstoken.to_string(),
+        privatetoken: privatetoken.to_string(),
+    };
+    write_private_file(tokens_path, &serde_json::to_string_pretty(&tokens)?).await
+}
+
+/// Creates a new session cookie from the tokens of the mobile app (no user interaction needed)
+///
+/// Uses `tool_mobile_get_autologin_key`, which moodle only allows via https
+/// and once every few minutes per user (6 by default)
+pub async fn session_from_private_token(
+    instance_url: &Url,
+    wstoken: &str,
+    privatetoken: &str,
+) -> Result<String> {
+    let cookie_jar = Arc::new(Jar::default());
+    let client = Client::builder()
+        .cookie_provider(cookie_jar.clone())
+        .build()?;
+    let api_url = instance_url.join("webservice/rest/server.php")?;
+
+    let autologin: Value = client
+        .post(api_url.clone())
+        .form(&[
+            ("moodlewsrestformat", "json"),
+            ("wstoken", wstoken),
+            ("wsfunction", "tool_mobile_get_autologin_key"),
+            ("privatetoken", privatetoken),
+        ])
+        .send()
+        .await?
+        .json()
+        .await?;
+    if let Some(message) = autologin["message"].as_str() {
+        return Err(anyhow!("Moodle refused the autologin key: {}", message));
+    }
+    let key = autologin["key"]
+        .as_str()
+        .ok_or(anyhow!("No autologin key in the response"))?;
+    register_secret(key);
+    let autologin_url = autologin["autologinurl"]
+        .as_str()
+        .ok_or(anyhow!("No autologin url in the response"))?;
+
+    // The autologin needs the user id
+    let site_info: Value = client
+        .post(api_url)
+        .form(&[
+            ("moodlewsrestformat", "json"),
+            ("wstoken", wstoken),
+            ("wsfunction", "core_webservice_get_site_info"),
+        ])
+        .send()
+        .await?
+        .json()
+        .await?;
+    let user_id = site_info["userid"]
+        .as_u64()
+        .ok_or(anyhow!("No user id in the site info"))?;
+
+    let response = client
+        .get(autologin_url)
+        .query(&[("userid", user_id.to_string().as_str()), ("key", key)])
+        .send()
+        .await?;
+    if is_login_url(response.url()) {
+        return Err(anyhow!("Moodle did not accept the autologin key"));
+    }
+    debug!("Completed autologin");
+
+    extract_session_cookie(instance_url, &cookie_jar)
+}
@@ -0,0 +1,89 @@
+use reqwest::{Client, cookie::Jar};
+use tracing::{debug, trace};
+
+use super::session::session_is_valid;
+use super::*;
+
+/// Creates a new session cookie from a login at an Apereo CAS server
+///
+/// Moodle redirects to the CAS login, which redirects back with a service ticket after posting the credentials
+pub async fn from_cas(
+    instance_url: &Url,
+    username: &str,
+    password: &str,
+    wstoken_request: bool,
+) -> Result<LoginParams> {
+    let cookie_jar = Arc::new(Jar::default());
+    let client = Client::builder()
+        .cookie_provider(cookie_jar.clone())
+        .build()?;
+
+    debug!("Logging in via CAS using username: \"{}\"", username);
+
+    // Step 1 (Get redirected to the CAS login form)
+    let response = client
+        .get(instance_url.join("login/index.php?authCAS=CAS")?)
+        .send()
+        .await?;
+    let resp_url = response.url().clone();
+    debug!("Response URL: {:?}", resp_url);
+    let html = response.text().await?;
+    trace!("Response HTML:\n {}", html);
+
+    // The document is not Send, therefore it must not live across an await
+    let (execution, login_ticket) = {
+        let document = Document::from(html.as_str());
+        let hidden_input = |name: &str| {
+            document
+                .find(Name("input"))
+                .find(|node| node.attr("name") == Some(name))
+                .and_then(|node| node.attr("value"))
+                .map(|value| value.to_string())
+        };
+        // The login ticket is only used by CAS versions before 5
+        (hidden_input("execution"), hidden_input("lt"))
+    };
+    let execution = execution.ok_or(anyhow!(
+        "Error on login: Couldn't extract the CAS execution token (is CAS enabled on this moodle?)"
+    ))?;
+    debug!("Completed login step 1");
+
+    // Step 2 (Post credentials, CAS redirects back to moodle with a service ticket)
+    let mut form = vec![
+        ("username", username),
+        ("password", password),
+        ("execution", &execution),
+        ("_eventId", "submit"),
+    ];
+    if let Some(login_ticket) = &login_ticket {
+        form.push(("lt", login_ticket));
+    }
+    let response = client.post(resp_url.clone()).form(&form).send().await?;
+    debug!("Response URL: {:?}", response.url());
+    // Wrong credentials show the CAS form again, a failed ticket validation the moodle login
+    let final_url = response.url();
+    if final_url.path() == resp_url.path() || is_login_url(final_url) {
+        return Err(anyhow!(
+            "Error on login: CAS did not log in to moodle (probably wrong login credentials)"
+        ));
+    }
+    debug!("Completed login step 2");
+
+    let session_cookie = extract_session_cookie(instance_url, &cookie_jar)?;
+    if !session_is_valid(instance_url, &session_cookie).await? {
+        return Err(anyhow!(
+            "Error on login: The session cookie after the CAS login is not logged in"
+        ));
+    }
+
+    let wstoken = if wstoken_request {
+        Some(request_wstoken(&client, instance_url).await?)
+    } else {
+        None
+    };
+
+    Ok(LoginParams {
+        cookie: session_cookie,
+        wstoken,
+    })
+}
@@ -45,14 +45,22 @@ pub async fn login_graphical(
         .event_listener::<chromiumoxide::cdp::browser_protocol::network::EventRequestWillBeSent>()
         .await?;
     while let Some(event) = events.next().await {
-        trace!("LoginEvent: {:?}", event);
         if event.document_url.starts_with("moo-dl://") {
-            debug!("Found \"moo-dl://\" event: {:?}", event);
             moo_dl_url = event.request.url.clone();
+            register_moo_dl_url(&event.document_url);
+            register_moo_dl_url(&moo_dl_url);
+            debug!("Found \"moo-dl://\" event: {:?}", event);
             break;
         }
+        trace!("LoginEvent: {:?}", event);
     }
     let all_cookies = browser.get_cookies().await?;
+    for cookie in all_cookies
+        .iter()
+        .filter(|cookie| cookie.name == "MoodleSession")
+    {
+        register_secret(&cookie.value);
+    }
     trace!("\nAll cookies: {:?}", all_cookies);
 
     // Browser is no longer needed, we can close it
@@ -0,0 +1,60 @@
+use std::io::IsTerminal;
+use std::path::Path;
+
+use dialoguer::Input;
+
+use super::autologin::{renew_session, save_mobile_tokens, session_from_private_token};
+use super::*;
+
+/// Creates a new session cookie, by logging in on another device (no local browser needed)
+///
+/// The user opens the printed url on any device and pastes the `moo-dl://token=...` url, that moodle redirects to.
+/// Afterwards the stored mobile app tokens are used to renew the session.
+pub async fn login_headless(
+    instance_url: &Url,
+    tokens_path: Option<&Path>,
+    wstoken_request: bool,
+) -> Result<LoginParams> {
+    if let Some(login_params) = renew_session(instance_url, tokens_path, wstoken_request).await {
+        return Ok(login_params);
+    }
+
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "Headless login: Logging in needs an interactive terminal (run moo-dl in a terminal once)"
+        ));
+    }
+
+    let launch_url = instance_url.join(
+        "admin/tool/mobile/launch.php?service=moodle_mobile_app&passport=00000&urlscheme=moo-dl",
+    )?;
+    println!(
+        "Open this url in a browser on any device and log in:\n\n{}\n\n\
+        Afterwards moodle redirects to an url starting with \"moo-dl://token=\", which the browser can not open.\n\
+        Firefox shows it in the address bar, Chrome in the developer console (F12).",
+        launch_url
+    );
+    let moo_dl_url: String = Input::new()
+        .with_prompt("Paste the moo-dl://token=... url")
+        .interact_text()?;
+    let moo_dl_url = moo_dl_url.trim();
+    if !moo_dl_url.starts_with("moo-dl://") {
+        return Err(anyhow!(
+            "Headless login: Expected an url starting with \"moo-dl://token=\""
+        ));
+    }
+
+    let (wstoken, privatetoken) = tokens_from_url(moo_dl_url)?;
+    let privatetoken = privatetoken.ok_or(anyhow!(
+        "Headless login: Moodle provided no private token, which is needed for getting a session"
+    ))?;
+    let cookie = session_from_private_token(instance_url, &wstoken, &privatetoken).await?;
+    if let Some(path) = tokens_path {
+        save_mobile_tokens(path, instance_url, &wstoken, &privatetoken).await?;
+    }
+
+    Ok(LoginParams {
+        cookie,
+        wstoken: wstoken_request.then_some(wstoken),
+    })
+}
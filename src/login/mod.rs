@@ -1,5 +1,12 @@
+pub mod autologin;
+pub mod cas;
 pub mod graphical;
+pub mod headless;
+pub mod oauth2;
 pub mod rwth;
+pub mod session;
+pub mod shibboleth;
+pub mod token;
 pub mod user_pass;
 
 use std::result::Result::Ok;
@@ -7,11 +14,18 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, anyhow};
+use autologin::{renew_session, session_from_private_token, stored_private_token};
+use cas::from_cas;
+use headless::login_headless;
+use oauth2::from_oauth2;
 use regex::Regex;
-use reqwest::Response;
 use reqwest::cookie::CookieStore;
+use reqwest::{Client, Response};
 use rwth::from_rwth;
+use session::{is_login_url, load_cached_session, save_session};
+use shibboleth::from_shibboleth;
 use tokio::time::sleep;
+use totp_rs::{Algorithm, Secret, TOTP};
 use tracing::{debug, info, trace, warn};
 use url::Url;
 
@@ -19,6 +33,7 @@ use select::{document::Document, predicate::Name};
 use user_pass::from_username_password;
 
 use crate::Result;
+use crate::config::secret::register_secret;
 use crate::config::sync_config::{Config, Login, LoginState};
 
 pub struct LoginParams {
@@ -29,11 +44,15 @@ pub struct LoginParams {
 impl Config {
     pub fn get_moodle_url(&self) -> &Url {
         match &self.login {
-            Login::ApiOnly { url } => url,
+            Login::ApiOnly { url, .. } => url,
             Login::Raw { url, .. } => url,
             Login::UserPass { url, .. } => url,
             Login::Graphical { url } => url,
+            Login::Headless { url } => url,
             Login::Rwth { url, .. } => url,
+            Login::Cas { url, .. } => url,
+            Login::Shibboleth { url, .. } => url,
+            Login::OAuth2 { url, .. } => url,
         }
     }
 
@@ -71,27 +90,174 @@ impl Config {
     async fn login(&self) -> Result<()> {
         let mut cookie_guard = self.cookie.write().await;
 
+        // Reuse the session of a previous run
+        let cacheable = !matches!(self.login, Login::Raw { .. });
+        if let Some(cache_path) = self.session_cache.as_ref().filter(|_| cacheable)
+            && let Some(cookie) = load_cached_session(cache_path, self.get_moodle_url()).await
+        {
+            // Using the session extends it
+            if let Err(e) = save_session(cache_path, self.get_moodle_url(), &cookie).await {
+                warn!("Failed caching the session: {}", e);
+            }
+            *cookie_guard = LoginState::Cookie {
+                cookie: Arc::new(cookie),
+            };
+            info!("Logged in using the cached session!");
+            return Ok(());
+        }
+
+        *cookie_guard = match self.fresh_login(true).await? {
+            None => LoginState::Unavailable,
+            Some(cookie) => {
+                if let Some(cache_path) = self.session_cache.as_ref().filter(|_| cacheable)
+                    && let Err(e) = save_session(cache_path, self.get_moodle_url(), &cookie).await
+                {
+                    warn!("Failed caching the session: {}", e);
+                }
+                LoginState::Cookie {
+                    cookie: Arc::new(cookie),
+                }
+            }
+        };
+        Ok(())
+    }
+
+    /// Replace an expired session by running the configured login again
+    ///
+    /// Concurrent callers with the same expired cookie only trigger one login.
+    /// Logins, that need interaction (e.g. graphical), fail instead of interrupting the sync.
+    /// # Returns
+    /// The new session cookie (None if the login method does not provide one)
+    pub async fn relogin(&self, expired: &Arc<String>) -> Result<Option<Arc<String>>> {
+        let _relogin_guard = self.relogin.lock().await;
+        if let LoginState::Cookie { cookie } = &*self.cookie.read().await
+            && cookie != expired
+        {
+            // Someone else already logged in again
+            return Ok(Some(cookie.clone()));
+        }
+
+        // The old cookie stays readable meanwhile (e.g. for the browser startup)
+        warn!("Session expired, logging in again");
+        let cookie = match self.fresh_login(false).await? {
+            Some(cookie) if cookie != **expired => Arc::new(cookie),
+            _ => {
+                return Err(anyhow!(
+                    "Session expired and the configured login did not provide a new one"
+                ));
+            }
+        };
+        if let Some(cache_path) = &self.session_cache
+            && let Err(e) = save_session(cache_path, self.get_moodle_url(), &cookie).await
+        {
+            warn!("Failed caching the session: {}", e);
+        }
+        *self.cookie.write().await = LoginState::Cookie {
+            cookie: cookie.clone(),
+        };
+
+        self.update_chromium_cookie(&cookie).await;
+        Ok(Some(cookie))
+    }
+
+    /// Send a GET request using the session cookie, logging in again if the session expired
+    /// # Returns
+    /// None, if no session is available
+    pub async fn get_with_session(&self, url: &str) -> Result<Option<Response>> {
+        let mut cookie = match self.get_cookie().await {
+            Some(cookie) => cookie,
+            None => return Ok(None),
+        };
+        for retry in [true, false] {
+            let response = self
+                .client
+                .get(url)
+                .header("Cookie", "MoodleSession=".to_string() + &cookie)
+                .send()
+                .await?;
+            if !is_login_url(response.url()) {
+                return Ok(Some(response));
+            }
+            if retry {
+                cookie = match self.relogin(&cookie).await? {
+                    Some(cookie) => cookie,
+                    None => return Ok(None),
+                };
+            }
+        }
+        Err(anyhow!("Got the login page, even after logging in again"))
+    }
+
+    /// Run the configured login (`interactive`: whether the user may be asked to sign in)
+    /// # Returns
+    /// The session cookie (None if the login method does not provide one)
+    async fn fresh_login(&self, interactive: bool) -> Result<Option<String>> {
+        if !interactive {
+            match &self.login {
+                Login::Graphical { .. } => {
+                    return Err(anyhow!(
+                        "The graphical login needs interaction, run moo-dl again to log in"
+                    ));
+                }
+                // Only the stored mobile app tokens can renew the session without signing in
+                Login::Headless { url } | Login::OAuth2 { url, .. } => {
+                    let login_result = renew_session(url, self.mobile_tokens.as_deref(), false)
+                        .await
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Signing in again needs interaction, run moo-dl again to log in"
+                            )
+                        })?;
+                    info!("Logged in using, the stored mobile app tokens!");
+                    return Ok(Some(login_result.cookie));
+                }
+                _ => {}
+            }
+        }
+
         match &self.login {
-            Login::ApiOnly { .. } => {
-                *cookie_guard = LoginState::Unavailable;
-                warn!("No full login method provided: Running with limited functionality");
-                Ok(())
+            Login::ApiOnly { url, private_token } => {
+                // Upgrade to a full login, if moodle allows autologin keys
+                let private_token = match private_token {
+                    Some(private_token) => Some(private_token.clone()),
+                    None => {
+                        stored_private_token(self.mobile_tokens.as_deref(), url, &self.wstoken)
+                            .await
+                    }
+                };
+                let Some(private_token) = private_token else {
+                    warn!("No full login method provided: Running with limited functionality");
+                    return Ok(None);
+                };
+                match session_from_private_token(url, &self.wstoken, &private_token).await {
+                    Ok(cookie) => {
+                        info!("Logged in using, the wstoken (autologin)!");
+                        Ok(Some(cookie))
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Could not get a session using the wstoken: Running with limited functionality ({})",
+                            e
+                        );
+                        Ok(None)
+                    }
+                }
             }
             Login::Raw { url: _, cookie } => {
-                *cookie_guard = LoginState::Cookie {
-                    cookie: Arc::new(cookie.to_string()),
-                };
                 info!("Logged in using, raw params!");
-                Ok(())
+                Ok(Some(cookie.to_string()))
             }
             Login::Graphical { url } => {
                 let login_result =
                     graphical::login_graphical(url, &self.chrome_executable, false).await?;
-                *cookie_guard = LoginState::Cookie {
-                    cookie: Arc::new(login_result.cookie),
-                };
                 info!("Logged in using, graphical!");
-                Ok(())
+                Ok(Some(login_result.cookie))
+            }
+            Login::Headless { url } => {
+                let login_result =
+                    login_headless(url, self.mobile_tokens.as_deref(), false).await?;
+                info!("Logged in using, headless!");
+                Ok(Some(login_result.cookie))
             }
             Login::UserPass {
                 url,
@@ -99,11 +265,8 @@ impl Config {
                 password,
             } => {
                 let login_result = from_username_password(url, username, password, false).await?;
-                *cookie_guard = LoginState::Cookie {
-                    cookie: Arc::new(login_result.cookie),
-                };
                 info!("Logged in using, username & password!");
-                Ok(())
+                Ok(Some(login_result.cookie))
             }
             Login::Rwth {
                 url,
@@ -114,28 +277,132 @@ impl Config {
             } => {
                 let login_result =
                     from_rwth(url, username, password, totp, totp_secret, false).await?;
-                *cookie_guard = LoginState::Cookie {
-                    cookie: Arc::new(login_result.cookie),
-                };
                 info!("Logged in using, RWTH SSO!");
-                Ok(())
+                Ok(Some(login_result.cookie))
+            }
+            Login::Cas {
+                url,
+                username,
+                password,
+            } => {
+                let login_result = from_cas(url, username, password, false).await?;
+                info!("Logged in using, CAS!");
+                Ok(Some(login_result.cookie))
+            }
+            Login::Shibboleth {
+                url,
+                username,
+                password,
+                totp_secret,
+                fields,
+            } => {
+                let login_result = from_shibboleth(
+                    url,
+                    username,
+                    password,
+                    totp_secret.as_deref(),
+                    fields,
+                    false,
+                )
+                .await?;
+                info!("Logged in using, Shibboleth SSO!");
+                Ok(Some(login_result.cookie))
+            }
+            Login::OAuth2 { url, issuer } => {
+                let login_result =
+                    from_oauth2(url, issuer.as_deref(), self.mobile_tokens.as_deref(), false)
+                        .await?;
+                info!("Logged in using, OAuth2!");
+                Ok(Some(login_result.cookie))
             }
         }
     }
 }
 
+/// Redact the token of a `moo-dl://token=...` url from the tracing output
+fn register_moo_dl_url(moo_dl_url: &str) {
+    if let Some(token_base64) = moo_dl_url.split("token=").last() {
+        register_secret(token_base64);
+    }
+}
+
 fn wstoken_from_url(moo_dl_url: &str) -> Result<String> {
+    Ok(tokens_from_url(moo_dl_url)?.0)
+}
+
+/// Decode a `moo-dl://token=...` url
+/// # Returns
+/// The wstoken and the private token (if moodle provided one)
+fn tokens_from_url(moo_dl_url: &str) -> Result<(String, Option<String>)> {
+    // The url parser may append a slash to the custom scheme url
     let token_base64 = match moo_dl_url.split("token=").last() {
-        Some(token_base64) => token_base64,
+        Some(token_base64) => token_base64.trim_end_matches('/'),
         None => {
             return Err(anyhow!("Error on login: No token found in url"));
         }
     };
+    register_secret(token_base64);
     let token_decoded = String::from_utf8(base64::Engine::decode(
         &base64::engine::general_purpose::STANDARD,
         token_base64,
     )?)?;
-    Ok(token_decoded.split(":::").collect::<Vec<&str>>()[1].to_string())
+    // Contains the signature, the wstoken and the private token
+    let parts: Vec<&str> = token_decoded.split(":::").collect();
+    for part in &parts {
+        register_secret(part);
+    }
+    let wstoken = parts
+        .get(1)
+        .ok_or(anyhow!("Error on login: No wstoken found in url"))?;
+    Ok((
+        wstoken.to_string(),
+        parts.get(2).map(|part| part.to_string()),
+    ))
+}
+
+/// Acquire a wstoken for the mobile app service, using a logged in client
+async fn request_wstoken(client: &Client, instance_url: &Url) -> Result<String> {
+    Ok(request_mobile_tokens(client, instance_url).await?.0)
+}
+
+/// Acquire the tokens of the mobile app service, using a logged in client
+///
+/// Moodle answers with a redirect to `moo-dl://token=...`, which reqwest can not follow
+/// # Returns
+/// The wstoken and the private token (if moodle provided one)
+async fn request_mobile_tokens(
+    client: &Client,
+    instance_url: &Url,
+) -> Result<(String, Option<String>)> {
+    let response_url = match client
+        .get(instance_url.join(
+            "admin/tool/mobile/launch.php?service=moodle_mobile_app&passport=00000&urlscheme=moo-dl",
+        )?)
+        .send()
+        .await
+    {
+        Ok(_) => return Err(anyhow!("This should have resulted in an invalid url")),
+        Err(e) => e.url().ok_or(anyhow!("No wstoken url"))?.to_string(),
+    };
+    // Decoding the token registers it as secret, so it has to happen before logging the url
+    let tokens = tokens_from_url(&response_url)?;
+    trace!("Response URL: {}", response_url);
+    debug!("Found Token {:?}", tokens.0);
+    Ok(tokens)
+}
+
+/// Generate the current code of a standard TOTP generator (SHA1, 6 digits, 30 seconds)
+fn generate_totp(totp_secret: &str) -> Result<String> {
+    let totp_generator = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(totp_secret.to_string()).to_bytes()?,
+    )?;
+    let totp_token = totp_generator.generate_current()?;
+    debug!("Generated TOTP token");
+    Ok(totp_token)
 }
 
 /// Get some sort of auth login token
@@ -165,7 +432,6 @@ fn extract_session_cookie<C: CookieStore + 'static>(
         .cookies(instance_url)
         .context("Cookie extractor: could not extract cookie")?;
     let header_value = header_value.to_str()?;
-    trace!("Header Values from instance_url: {:?}", header_value);
 
     let regex = Regex::new(r"MoodleSession=([^ ;]+)")?;
     let regex_capture = regex
@@ -178,7 +444,9 @@ fn extract_session_cookie<C: CookieStore + 'static>(
         .nth(1)
         .ok_or(anyhow!("Cookie extractor: could not extract cookie"))?
         .to_string();
+    register_secret(&session_cookie);
 
+    trace!("Header Values from instance_url: {:?}", header_value);
     debug!("Found Session Cookie {}", session_cookie);
     Ok(session_cookie)
 }
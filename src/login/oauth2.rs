@@ -0,0 +1,145 @@
+use std::io::IsTerminal;
+use std::path::Path;
+
+use dialoguer::Input;
+use reqwest::{Client, cookie::Jar, header::LOCATION, redirect::Policy};
+use tracing::{debug, warn};
+
+use super::autologin::{renew_session, save_mobile_tokens};
+use super::session::is_login_url;
+use super::*;
+
+/// Creates a new session cookie from a login via an OAuth2 provider (e.g. Microsoft, Google, Keycloak)
+///
+/// Moodle is the OAuth2 client, therefore the sign in has to happen in a browser once:
+/// The user opens the printed url on any device and pastes the url of the redirect back to moodle.
+/// Afterwards the stored mobile app tokens are used to renew the session.
+///
+/// A loopback or device code flow is not possible: The provider only redirects to moodle's
+/// `admin/oauth2callback.php` and only moodle knows the client secret (and gets the refresh token).
+pub async fn from_oauth2(
+    instance_url: &Url,
+    issuer: Option<&str>,
+    tokens_path: Option<&Path>,
+    wstoken_request: bool,
+) -> Result<LoginParams> {
+    if let Some(login_params) = renew_session(instance_url, tokens_path, wstoken_request).await {
+        return Ok(login_params);
+    }
+
+    if !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "OAuth2 login: Signing in needs an interactive terminal (run moo-dl in a terminal once)"
+        ));
+    }
+
+    let cookie_jar = Arc::new(Jar::default());
+    let client = Client::builder()
+        .cookie_provider(cookie_jar.clone())
+        .build()?;
+    // Moodle redirects to the OAuth2 provider, we need the url instead
+    let client_no_redirect = Client::builder()
+        .cookie_provider(cookie_jar.clone())
+        .redirect(Policy::none())
+        .build()?;
+
+    // The login link contains the sesskey of our session
+    let html = client
+        .get(instance_url.join("login/index.php")?)
+        .send()
+        .await?
+        .text()
+        .await?;
+    let login_link = find_oauth2_link(&html, issuer)?;
+    debug!("OAuth2 login link: {}", login_link);
+
+    let response = client_no_redirect
+        .get(instance_url.join(&login_link)?)
+        .send()
+        .await?;
+    let authorize_url = response
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .ok_or(anyhow!(
+            "OAuth2 login: Moodle did not redirect to the provider"
+        ))?
+        .to_string();
+
+    println!(
+        "Open this url in any browser and sign in:\n\n{}\n\n\
+        Afterwards moodle shows an error page (invalid session key), this is expected.",
+        authorize_url
+    );
+    let callback_url: String = Input::new()
+        .with_prompt("Paste the url of that page (.../admin/oauth2callback.php?...)")
+        .interact_text()?;
+    let callback_url = Url::parse(callback_url.trim())?;
+    if !callback_url.path().ends_with("/admin/oauth2callback.php") {
+        return Err(anyhow!(
+            "OAuth2 login: Expected the url of admin/oauth2callback.php, got {}",
+            callback_url.path()
+        ));
+    }
+    for (_, value) in callback_url.query_pairs() {
+        register_secret(&value);
+    }
+
+    // Moodle redeems the code for our session
+    let response = client.get(callback_url).send().await?;
+    if is_login_url(response.url()) {
+        return Err(anyhow!("OAuth2 login: Moodle did not accept the sign in"));
+    }
+    let session_cookie = extract_session_cookie(instance_url, &cookie_jar)?;
+
+    let (wstoken, privatetoken) = request_mobile_tokens(&client, instance_url).await?;
+    match (tokens_path, privatetoken) {
+        (Some(path), Some(privatetoken)) => {
+            save_mobile_tokens(path, instance_url, &wstoken, &privatetoken).await?
+        }
+        (Some(_), None) => warn!(
+            "Moodle provided no private token: Signing in is needed again, once the session expires"
+        ),
+        (None, _) => {}
+    }
+
+    Ok(LoginParams {
+        cookie: session_cookie,
+        wstoken: wstoken_request.then_some(wstoken),
+    })
+}
+
+/// Find the login link of an OAuth2 provider on the moodle login page
+fn find_oauth2_link(html: &str, issuer: Option<&str>) -> Result<String> {
+    let document = Document::from(html);
+    let links: Vec<(String, String)> = document
+        .find(Name("a"))
+        .filter_map(|node| {
+            let href = node.attr("href")?;
+            href.contains("auth/oauth2/login.php")
+                .then(|| (node.text().trim().to_string(), href.to_string()))
+        })
+        .collect();
+
+    let matching: Vec<&(String, String)> = match issuer {
+        Some(issuer) => links
+            .iter()
+            .filter(|(name, _)| name.to_lowercase().contains(&issuer.to_lowercase()))
+            .collect(),
+        None => links.iter().collect(),
+    };
+    match matching.as_slice() {
+        [(_, href)] => Ok(href.clone()),
+        [] if links.is_empty() => Err(anyhow!(
+            "OAuth2 login: The moodle login page offers no OAuth2 providers"
+        )),
+        _ => Err(anyhow!(
+            "OAuth2 login: Set issuer to one of the providers: {}",
+            links
+                .iter()
+                .map(|(name, _)| format!("\"{}\"", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
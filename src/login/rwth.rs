@@ -1,6 +1,5 @@
 use reqwest::{Client, cookie::Jar};
 use tokio::task;
-use totp_rs::{Algorithm, Secret, TOTP};
 use tracing::{debug, trace};
 
 use super::*;
@@ -17,14 +16,13 @@ pub async fn from_rwth(
     wstoken_request: bool,
 ) -> Result<LoginParams> {
     let cookie_jar = Arc::new(Jar::default());
-    let totp_secret: String = totp_secret.into();
     let client = Client::builder()
         .cookie_provider(cookie_jar.clone())
         .build()?;
 
     debug!(
-        "Logging in via RWTH sso using username: \"{}\", password: \"{}\", totp: \"{}\", totp_secret: \"{}\"",
-        username, password, totp, totp_secret
+        "Logging in via RWTH sso using username: \"{}\", totp: \"{}\"",
+        username, totp
     );
 
     // Intialize login process
@@ -73,16 +71,7 @@ pub async fn from_rwth(
     debug!("Completed login step 2");
 
     // Step 3 (Provide 2nd factor)
-    // Generate token
-    let totp_generator = TOTP::new(
-        Algorithm::SHA1,
-        6,
-        1,
-        30,
-        Secret::Encoded(totp_secret).to_bytes()?,
-    )?;
-    let totp_token = totp_generator.generate_current().unwrap();
-    debug!("Generated token: {}", totp_token);
+    let totp_token = generate_totp(totp_secret)?;
     let response = client
         .post(resp_url)
         .form(&vec![
@@ -139,21 +128,7 @@ pub async fn from_rwth(
 
     // Check if wstoken we need to get a new wstoken
     let wstoken = if wstoken_request {
-        // Acuire wstoken
-        let response_url = match client
-            .get(instance_url.join("admin/tool/mobile/launch.php?service=moodle_mobile_app&passport=00000&urlscheme=moo-dl")?)
-            .send()
-            .await {
-                Ok(_) => return Err(anyhow!("This should have resulted in an invalid url")),
-                Err(e) => {
-                    trace!("Attempting to parse theoretical Error: {:?}", e);
-                    e.url().ok_or(anyhow!("No wstoken url"))?.to_string()
-                }
-            };
-        trace!("Response URL: {}", response_url);
-        let wstoken = wstoken_from_url(&response_url)?;
-        debug!("Found Token {:?}", wstoken);
-        Some(wstoken)
+        Some(request_wstoken(&client, instance_url).await?)
     } else {
         None
     };
@@ -0,0 +1,114 @@
+use std::path::Path;
+
+use chrono::Utc;
+use reqwest::{Client, StatusCode, redirect::Policy};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt};
+use tracing::{debug, warn};
+
+use super::*;
+
+/// File in the sync dir, that caches the session between runs
+pub const SESSION_CACHE_FILE: &str = ".moo-dl.session.json";
+
+/// Moodle's default session timeout (the server does not tell us the real one)
+const SESSION_LIFETIME: u64 = 8 * 60 * 60;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedSession {
+    url: Url,
+    cookie: String,
+    /// Unix timestamp, after which the session is considered dead
+    expires: u64,
+}
+
+/// Check if an url is (part of) the moodle login, which means the session is not valid (anymore)
+pub fn is_login_url(url: &Url) -> bool {
+    let path = url.path();
+    path.ends_with("/login/index.php")
+        || path.contains("/auth/shibboleth/")
+        || path.ends_with("/login/")
+}
+
+/// Check if a saved html page is the moodle login form (instead of the requested content)
+pub fn is_login_page(html: &str) -> bool {
+    html.contains("name=\"logintoken\"") && html.contains("/login/index.php")
+}
+
+/// Check if a session cookie is still logged in (without following redirects)
+pub async fn session_is_valid(instance_url: &Url, cookie: &str) -> Result<bool> {
+    let client = Client::builder().redirect(Policy::none()).build()?;
+    let response = client
+        .get(instance_url.join("user/preferences.php")?)
+        .header("Cookie", format!("MoodleSession={}", cookie))
+        .send()
+        .await?;
+
+    if response.status().is_redirection() {
+        return Ok(false);
+    }
+    Ok(response.status() == StatusCode::OK && !is_login_url(response.url()))
+}
+
+/// Load the cached session, if it belongs to this moodle instance and is still valid
+pub async fn load_cached_session(cache_path: &Path, instance_url: &Url) -> Option<String> {
+    let contents = fs::read_to_string(cache_path).await.ok()?;
+    let session: CachedSession = match serde_json::from_str(&contents) {
+        Ok(session) => session,
+        Err(e) => {
+            warn!("Ignoring invalid session cache: {}", e);
+            return None;
+        }
+    };
+    register_secret(&session.cookie);
+
+    if &session.url != instance_url || session.expires < Utc::now().timestamp() as u64 {
+        debug!("Cached session expired");
+        return None;
+    }
+    match session_is_valid(instance_url, &session.cookie).await {
+        Ok(true) => Some(session.cookie),
+        Ok(false) => {
+            debug!("Cached session is no longer logged in");
+            None
+        }
+        Err(e) => {
+            debug!("Could not validate cached session: {}", e);
+            None
+        }
+    }
+}
+
+/// Cache a session cookie for the next runs
+pub async fn save_session(cache_path: &Path, instance_url: &Url, cookie: &str) -> Result<()> {
+    let session = CachedSession {
+        url: instance_url.clone(),
+        cookie: cookie.to_string(),
+        expires: Utc::now().timestamp() as u64 + SESSION_LIFETIME,
+    };
+    write_private_file(cache_path, &serde_json::to_string_pretty(&session)?).await
+}
+
+/// Write a file, that is only readable by the current user (it contains credentials)
+pub async fn write_private_file(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // The permissions have to be set on creation, otherwise the contents are readable in between
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    // Files of older versions may have been created with the default permissions
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))
+            .await?;
+    }
+    file.write_all(contents.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,467 @@
+use reqwest::{Client, cookie::Jar};
+use select::predicate::Or;
+use tracing::{debug, trace};
+
+use super::session::is_login_url;
+use super::*;
+use crate::config::sync_config::ShibbolethFields;
+
+/// Upper bound of forms to pass, before giving up (login, TOTP, consent, SAML post, ...)
+const MAX_LOGIN_STEPS: usize = 8;
+
+/// A html form, with the values the browser would submit without user input
+struct HtmlForm {
+    action: Url,
+    /// Hidden inputs and the first named submit button
+    values: Vec<(String, String)>,
+    /// Names of all inputs
+    inputs: Vec<String>,
+}
+
+impl HtmlForm {
+    fn has_input(&self, name: &str) -> bool {
+        self.inputs.iter().any(|input| input == name)
+    }
+}
+
+/// Creates a new session cookie from a login at a Shibboleth identity provider
+///
+/// Follows the redirect from moodle to the identity provider, submits the configured fields
+/// and posts the `SAMLResponse` back to moodle
+pub async fn from_shibboleth(
+    instance_url: &Url,
+    username: &str,
+    password: &str,
+    totp_secret: Option<&str>,
+    fields: &ShibbolethFields,
+    wstoken_request: bool,
+) -> Result<LoginParams> {
+    let cookie_jar = Arc::new(Jar::default());
+    let client = Client::builder()
+        .cookie_provider(cookie_jar.clone())
+        .build()?;
+
+    debug!("Logging in via Shibboleth using username: \"{}\"", username);
+
+    // Intialize login process
+    client.get(instance_url.as_ref()).send().await?;
+    let mut response = client
+        .get(instance_url.join("auth/shibboleth/index.php")?)
+        .send()
+        .await?;
+
+    let mut password_sent = false;
+    let mut totp_sent = false;
+    for step in 1..=MAX_LOGIN_STEPS {
+        let resp_url = response.url().clone();
+        debug!("Response URL: {:?}", resp_url);
+        let html = response.text().await?;
+        trace!("Response HTML:\n {}", html);
+
+        let form = parse_form(&resp_url, &html, fields).ok_or_else(|| {
+            anyhow!(
+                "Shibboleth login: No form found on {} (step {})",
+                resp_url,
+                step
+            )
+        })?;
+        let mut values = form.values.clone();
+
+        if form.has_input("SAMLResponse") {
+            // Pass tokens to moodle
+            let response = client.post(form.action).form(&values).send().await?;
+            if is_login_url(response.url()) {
+                return Err(anyhow!(
+                    "Shibboleth login: Moodle did not accept the SAMLResponse"
+                ));
+            }
+            debug!("Completed login step {} (SAMLResponse)", step);
+
+            let session_cookie = extract_session_cookie(instance_url, &cookie_jar)?;
+            let wstoken = if wstoken_request {
+                Some(request_wstoken(&client, instance_url).await?)
+            } else {
+                None
+            };
+            return Ok(LoginParams {
+                cookie: session_cookie,
+                wstoken,
+            });
+        } else if form.has_input(&fields.password) {
+            if password_sent {
+                return Err(anyhow!(
+                    "Shibboleth login: The identity provider rejected the username or password"
+                ));
+            }
+            password_sent = true;
+            if form.has_input(&fields.username) {
+                values.push((fields.username.clone(), username.to_string()));
+            }
+            values.push((fields.password.clone(), password.to_string()));
+            debug!("Completed login step {} (password)", step);
+        } else if form.has_input(&fields.username) {
+            // Some identity providers ask for the username on a separate page
+            values.push((fields.username.clone(), username.to_string()));
+            debug!("Completed login step {} (username)", step);
+        } else if form.has_input(&fields.totp) {
+            let totp_secret = totp_secret.ok_or_else(|| {
+                anyhow!("Shibboleth login: The identity provider asks for a TOTP, but no totp_secret is configured")
+            })?;
+            if totp_sent {
+                return Err(anyhow!(
+                    "Shibboleth login: The identity provider rejected the TOTP"
+                ));
+            }
+            totp_sent = true;
+            values.push((fields.totp.clone(), generate_totp(totp_secret)?));
+            debug!("Completed login step {} (TOTP)", step);
+        } else {
+            // Forms without user input (e.g. local storage check, attribute release)
+            debug!("Completed login step {} (passing form)", step);
+        }
+
+        response = client.post(form.action).form(&values).send().await?;
+    }
+
+    Err(anyhow!(
+        "Shibboleth login: No SAMLResponse after {} steps",
+        MAX_LOGIN_STEPS
+    ))
+}
+
+/// Find the relevant form of a page (the one asking for credentials or passing the SAMLResponse)
+fn parse_form(page_url: &Url, html: &str, fields: &ShibbolethFields) -> Option<HtmlForm> {
+    let document = Document::from(html);
+    let relevant = [
+        "SAMLResponse",
+        &fields.username,
+        &fields.password,
+        &fields.totp,
+    ];
+    let forms: Vec<_> = document.find(Name("form")).collect();
+    let form = forms
+        .iter()
+        .find(|form| {
+            form.find(Name("input")).any(|input| {
+                input
+                    .attr("name")
+                    .is_some_and(|name| relevant.contains(&name))
+            })
+        })
+        .or_else(|| forms.first())?;
+
+    let action = match form.attr("action") {
+        Some(action) if !action.is_empty() => page_url.join(action).ok()?,
+        _ => page_url.clone(),
+    };
+
+    let mut values = vec![];
+    let mut inputs = vec![];
+    let mut submit_found = false;
+    for input in form.find(Or(Name("input"), Name("button"))) {
+        let Some(name) = input.attr("name") else {
+            continue;
+        };
+        inputs.push(name.to_string());
+        let value = input.attr("value").unwrap_or_default().to_string();
+        let input_type = input.attr("type").unwrap_or_default();
+        let is_submit =
+            input_type == "submit" || (input.name() == Some("button") && input_type.is_empty());
+        if input_type == "hidden" {
+            values.push((name.to_string(), value));
+        } else if is_submit && !submit_found {
+            // Only the clicked button is submitted
+            submit_found = true;
+            values.push((name.to_string(), value));
+        }
+    }
+
+    Some(HtmlForm {
+        action,
+        values,
+        inputs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    const TOTP_SECRET: &str = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP";
+
+    const LOGIN_PAGE: &str = r#"<html><body>
+        <form action="/search" method="get"><input name="q"></form>
+        <form action="/idp/profile/SAML2/Redirect/SSO?execution=e1s2" method="post">
+            <input type="hidden" name="csrf_token" value="c&amp;1">
+            <input name="j_username">
+            <input type="password" name="j_password">
+            <input type="checkbox" name="donotcache" value="1">
+            <button type="submit" name="_eventId_proceed">Login</button>
+            <button type="submit" name="_eventId_cancel">Cancel</button>
+        </form></body></html>"#;
+
+    const TOTP_PAGE: &str = r#"<html><body><form method="post">
+            <input type="hidden" name="csrf_token" value="c2">
+            <input name="j_tokenNumber" autocomplete="one-time-code">
+            <input type="submit" name="_eventId_proceed" value="Continue">
+        </form></body></html>"#;
+
+    fn saml_page(moodle: &str) -> String {
+        format!(
+            r#"<html><body onload="document.forms[0].submit()">
+            <form action="{}/Shibboleth.sso/SAML2/POST" method="post">
+                <input type="hidden" name="RelayState" value="ss:mem:1">
+                <input type="hidden" name="SAMLResponse" value="PHNhbWw+">
+                <noscript><input type="submit" value="Continue"></noscript>
+            </form></body></html>"#,
+            moodle
+        )
+    }
+
+    fn form_value<'a>(form: &'a HtmlForm, name: &str) -> Option<&'a str> {
+        form.values
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn parse_form_username_password() {
+        let page_url =
+            Url::parse("https://idp.example.com/idp/profile/SAML2/Redirect/SSO?execution=e1s1")
+                .unwrap();
+        let form = parse_form(&page_url, LOGIN_PAGE, &ShibbolethFields::default()).unwrap();
+
+        // The search form comes first, but does not ask for credentials
+        assert_eq!(
+            form.action.as_str(),
+            "https://idp.example.com/idp/profile/SAML2/Redirect/SSO?execution=e1s2"
+        );
+        assert!(form.has_input("j_username"));
+        assert!(form.has_input("j_password"));
+        assert_eq!(form_value(&form, "csrf_token"), Some("c&1"));
+        // Only the first button is clicked, user inputs are left to the login
+        assert_eq!(form_value(&form, "_eventId_proceed"), Some(""));
+        assert_eq!(form_value(&form, "_eventId_cancel"), None);
+        assert_eq!(form_value(&form, "j_username"), None);
+        assert_eq!(form_value(&form, "donotcache"), None);
+    }
+
+    #[test]
+    fn parse_form_totp() {
+        let page_url =
+            Url::parse("https://idp.example.com/idp/profile/SAML2/Redirect/SSO?execution=e1s3")
+                .unwrap();
+        let form = parse_form(&page_url, TOTP_PAGE, &ShibbolethFields::default()).unwrap();
+
+        // Forms without action are posted to the page itself
+        assert_eq!(form.action, page_url);
+        assert!(form.has_input("j_tokenNumber"));
+        assert!(!form.has_input("j_password"));
+        assert_eq!(
+            form.values,
+            vec![
+                ("csrf_token".to_string(), "c2".to_string()),
+                ("_eventId_proceed".to_string(), "Continue".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_form_saml_response() {
+        let page_url =
+            Url::parse("https://idp.example.com/idp/profile/SAML2/Redirect/SSO?execution=e1s4")
+                .unwrap();
+        let html = saml_page("https://moodle.example.com");
+        let form = parse_form(&page_url, &html, &ShibbolethFields::default()).unwrap();
+
+        assert_eq!(
+            form.action.as_str(),
+            "https://moodle.example.com/Shibboleth.sso/SAML2/POST"
+        );
+        assert!(form.has_input("SAMLResponse"));
+        assert_eq!(form_value(&form, "SAMLResponse"), Some("PHNhbWw+"));
+        assert_eq!(form_value(&form, "RelayState"), Some("ss:mem:1"));
+    }
+
+    #[test]
+    fn parse_form_without_form() {
+        let page_url = Url::parse("https://idp.example.com/").unwrap();
+        assert!(
+            parse_form(
+                &page_url,
+                "<html>Error</html>",
+                &ShibbolethFields::default()
+            )
+            .is_none()
+        );
+    }
+
+    /// Minimal moodle and identity provider, that answers every request with `Connection: close`
+    fn spawn_stub() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let moodle = base.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let body = String::from_utf8(body).unwrap();
+
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default();
+                let path = parts.next().unwrap_or_default();
+                let (status, headers, page) = stub_response(&moodle, method, path, &body);
+                let response = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    page.len(),
+                    page
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        Url::parse(&format!("{}/", base)).unwrap()
+    }
+
+    fn stub_response(
+        moodle: &str,
+        method: &str,
+        path: &str,
+        body: &str,
+    ) -> (&'static str, String, String) {
+        let redirect = |location: &str| format!("Location: {}\r\n", location);
+        let sso = "/idp/profile/SAML2/Redirect/SSO";
+        match (method, path) {
+            ("GET", "/") => ("200 OK", String::new(), "moodle".to_string()),
+            ("GET", "/auth/shibboleth/index.php") => (
+                "302 Found",
+                redirect(&format!("{}?execution=e1s1", sso)),
+                String::new(),
+            ),
+            ("GET", path) if path.starts_with(sso) => {
+                ("200 OK", String::new(), LOGIN_PAGE.to_string())
+            }
+            // The TOTP form has no action, it is posted to the same url as the credentials
+            ("POST", path) if path == format!("{}?execution=e1s2", sso) => {
+                let totp = body
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("j_tokenNumber="));
+                let page = match totp {
+                    Some(totp) if totp.len() == 6 && body.contains("csrf_token=c2") => {
+                        saml_page(moodle)
+                    }
+                    Some(_) => TOTP_PAGE.to_string(),
+                    None if body.contains("csrf_token=c%261")
+                        && body.contains("j_username=user")
+                        && body.contains("j_password=secret")
+                        && body.contains("_eventId_proceed=") =>
+                    {
+                        TOTP_PAGE.to_string()
+                    }
+                    None => LOGIN_PAGE.to_string(),
+                };
+                ("200 OK", String::new(), page)
+            }
+            ("POST", "/Shibboleth.sso/SAML2/POST") => {
+                if body.contains("SAMLResponse=PHNhbWw%2B")
+                    && body.contains("RelayState=ss%3Amem%3A1")
+                {
+                    (
+                        "303 See Other",
+                        format!(
+                            "{}Set-Cookie: MoodleSession=sess42; path=/\r\n",
+                            redirect("/my/")
+                        ),
+                        String::new(),
+                    )
+                } else {
+                    ("303 See Other", redirect("/login/index.php"), String::new())
+                }
+            }
+            _ => ("200 OK", String::new(), "page".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn login_follows_forms() {
+        let instance_url = spawn_stub();
+        let params = from_shibboleth(
+            &instance_url,
+            "user",
+            "secret",
+            Some(TOTP_SECRET),
+            &ShibbolethFields::default(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(params.cookie, "sess42");
+        assert!(params.wstoken.is_none());
+    }
+
+    #[tokio::test]
+    async fn login_wrong_password() {
+        let instance_url = spawn_stub();
+        let error = from_shibboleth(
+            &instance_url,
+            "user",
+            "wrong",
+            Some(TOTP_SECRET),
+            &ShibbolethFields::default(),
+            false,
+        )
+        .await
+        .err()
+        .unwrap();
+
+        assert!(
+            error
+                .to_string()
+                .contains("rejected the username or password")
+        );
+    }
+
+    #[tokio::test]
+    async fn login_totp_without_secret() {
+        let instance_url = spawn_stub();
+        let error = from_shibboleth(
+            &instance_url,
+            "user",
+            "secret",
+            None,
+            &ShibbolethFields::default(),
+            false,
+        )
+        .await
+        .err()
+        .unwrap();
+
+        assert!(error.to_string().contains("no totp_secret is configured"));
+    }
+}
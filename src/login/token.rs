@@ -0,0 +1,215 @@
+use std::io::IsTerminal;
+use std::path::Path;
+
+use dialoguer::Confirm;
+use tracing::warn;
+
+use super::*;
+use crate::api::error::{MoodleErrorKind, api_error};
+use crate::config::yaml::yaml_string;
+
+/// Values, that reference a secret instead of containing it (see `config::secret`)
+const SECRET_REFERENCES: [&str; 3] = ["env:", "file:", "command:"];
+
+impl Config {
+    /// Check the wstoken and get a new one from the configured login, if moodle does not accept it anymore
+    ///
+    /// Offers to write the new token to the config file
+    pub async fn renew_invalid_wstoken(&mut self, config_path: &Path) -> Result<()> {
+        let error = match self.api_acquire_user_id().await {
+            Ok(_) => return Ok(()),
+            Err(error) => error,
+        };
+        match api_error(&error).map(|error| error.kind()) {
+            Some(MoodleErrorKind::InvalidToken) => {}
+            // Anything else (e.g. being offline) shows up during the sync
+            _ => {
+                warn!("Could not validate the wstoken: {}", error);
+                return Ok(());
+            }
+        }
+
+        warn!("The wstoken is invalid (revoked or expired), requesting a new one");
+        let wstoken = self
+            .fresh_wstoken()
+            .await
+            .context("Failed requesting a new wstoken")?
+            .ok_or(anyhow!(
+                "The wstoken is invalid and the login method can not provide a new one: Please update it in the config"
+            ))?;
+        register_secret(&wstoken);
+        self.wstoken = wstoken;
+        info!("Got a new wstoken");
+
+        if std::io::stdin().is_terminal()
+            && Confirm::new()
+                .with_prompt("Write the new wstoken to the config?")
+                .default(true)
+                .interact()?
+            && let Err(e) = write_wstoken(config_path, self.instance_index, &self.wstoken).await
+        {
+            warn!("Could not write the new wstoken to the config: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Run the configured login and request a new wstoken
+    /// # Returns
+    /// None, if the login method can not provide a wstoken
+    pub async fn fresh_wstoken(&self) -> Result<Option<String>> {
+        let login_result = match &self.login {
+            Login::Graphical { url } => {
+                graphical::login_graphical(url, &self.chrome_executable, true).await?
+            }
+            Login::UserPass {
+                url,
+                username,
+                password,
+            } => from_username_password(url, username, password, true).await?,
+            Login::Rwth {
+                url,
+                username,
+                password,
+                totp,
+                totp_secret,
+            } => from_rwth(url, username, password, totp, totp_secret, true).await?,
+            Login::Cas {
+                url,
+                username,
+                password,
+            } => from_cas(url, username, password, true).await?,
+            Login::Shibboleth {
+                url,
+                username,
+                password,
+                totp_secret,
+                fields,
+            } => {
+                from_shibboleth(
+                    url,
+                    username,
+                    password,
+                    totp_secret.as_deref(),
+                    fields,
+                    true,
+                )
+                .await?
+            }
+            _ => return Ok(None),
+        };
+        Ok(login_result.wstoken)
+    }
+}
+
+/// Replace the wstoken in the config file (keeping everything else as is)
+///
+/// Instances have their wstoken in the order of `instances`
+async fn write_wstoken(
+    config_path: &Path,
+    instance_index: Option<usize>,
+    wstoken: &str,
+) -> Result<()> {
+    let contents = tokio::fs::read_to_string(config_path).await?;
+    let contents = replace_wstoken(&contents, instance_index, wstoken)?;
+    tokio::fs::write(config_path, contents.as_bytes()).await?;
+    info!("Wrote the new wstoken to {}", config_path.display());
+    Ok(())
+}
+
+fn replace_wstoken(contents: &str, instance_index: Option<usize>, wstoken: &str) -> Result<String> {
+    // The value is quoted or plain (up to a comment)
+    let regex = Regex::new(
+        r#"(?m)^[ \t]*(?:- )?wstoken:[ \t]*("(?:[^"\\\n]|\\.)*"|'(?:[^'\n]|'')*'|[^\s"'#]\S*(?:[ \t]+[^\s#]\S*)*|)[ \t]*(?:#.*)?$"#,
+    )?;
+    let captures = regex
+        .captures_iter(contents)
+        .nth(instance_index.unwrap_or(0))
+        .ok_or(anyhow!("No wstoken found in the config"))?;
+    let value = captures
+        .get(1)
+        .ok_or(anyhow!("No wstoken found in the config"))?;
+    let (start, end, value) = (value.start(), value.end(), value.as_str());
+
+    let current: String = serde_yml::from_str(value).unwrap_or_default();
+    if SECRET_REFERENCES
+        .iter()
+        .any(|prefix| current.starts_with(prefix))
+    {
+        return Err(anyhow!(
+            "The wstoken is a reference ({}): Please update the referenced secret",
+            current
+        ));
+    }
+
+    // Keep the quotes of the current value
+    let wstoken = if value.starts_with('"') {
+        serde_json::to_string(wstoken)?
+    } else if value.starts_with('\'') {
+        format!("'{}'", wstoken.replace('\'', "''"))
+    } else {
+        yaml_string(wstoken)
+    };
+    // `wstoken:` may have no value (e.g. directly followed by a comment)
+    let before = &contents[..start];
+    let after = &contents[end..];
+    Ok(format!(
+        "{}{}{}{}{}",
+        before,
+        if before.ends_with(':') { " " } else { "" },
+        wstoken,
+        if after.starts_with('#') { " " } else { "" },
+        after
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_wstoken_keeps_quotes_and_comments() {
+        let contents = "wstoken: old # from the app\n";
+        assert_eq!(
+            replace_wstoken(contents, None, "new").unwrap(),
+            "wstoken: new # from the app\n"
+        );
+        let contents = "wstoken: \"old\"\n";
+        assert_eq!(
+            replace_wstoken(contents, None, "new").unwrap(),
+            "wstoken: \"new\"\n"
+        );
+        let contents = "wstoken: 'old'  # comment\n";
+        assert_eq!(
+            replace_wstoken(contents, None, "new").unwrap(),
+            "wstoken: 'new'  # comment\n"
+        );
+        assert_eq!(
+            replace_wstoken("wstoken:\nuser_id: 1\n", None, "new").unwrap(),
+            "wstoken: new\nuser_id: 1\n"
+        );
+        assert_eq!(
+            replace_wstoken("wstoken: # paste it here\n", None, "new").unwrap(),
+            "wstoken: new # paste it here\n"
+        );
+        let contents = "instances:\n  - name: a\n    wstoken: a\n  - wstoken: b\n";
+        assert_eq!(
+            replace_wstoken(contents, Some(1), "1e3").unwrap(),
+            "instances:\n  - name: a\n    wstoken: a\n  - wstoken: \"1e3\"\n"
+        );
+    }
+
+    #[test]
+    fn replace_wstoken_rejects_references() {
+        for contents in [
+            "wstoken: env:TOKEN\n",
+            "wstoken: \"env:TOKEN\" # comment\n",
+            "wstoken: 'file:/run/token'\n",
+        ] {
+            assert!(
+                replace_wstoken(contents, None, "new").is_err(),
+                "{}",
+                contents
+            );
+        }
+    }
+}
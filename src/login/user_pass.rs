@@ -31,6 +31,11 @@ pub async fn from_username_password(
             .await?
             .json()
             .await?;
+        for token in ["token", "privatetoken"] {
+            if let Some(token) = wstoken_req[token].as_str() {
+                register_secret(token);
+            }
+        }
         debug!("Response token: {:?}", wstoken_req);
         Some(wstoken_req["token"]
             .as_str()
@@ -58,11 +63,11 @@ pub async fn from_username_password(
         ])
         .send()
         .await?;
+    let session_cookie = extract_session_cookie(&instance_url, &cookie_jar)?;
     trace!(
         "Cookies: {:?}",
         session_cookie_req.cookies().collect::<Vec<_>>()
     );
-    let session_cookie = extract_session_cookie(&instance_url, &cookie_jar)?;
 
     Ok(LoginParams {
         cookie: session_cookie,
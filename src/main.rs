@@ -1,5 +1,7 @@
 mod api;
 mod config;
+mod courses;
+mod deadlines;
 mod download;
 mod generate_config;
 mod login;
@@ -9,6 +11,8 @@ mod update;
 
 use std::sync::Arc;
 
+use futures::future::join_all;
+
 // Animations and logging
 use tracing_indicatif::IndicatifLayer;
 use tracing_subscriber::{
@@ -17,9 +21,14 @@ use tracing_subscriber::{
 
 pub use anyhow::Result;
 
+use config::check::check_config;
 use config::cli;
+use config::secret::RedactingMakeWriter;
 use config::sync_config::{Config, read_config};
+use courses::manage_courses;
+use deadlines::list_deadlines;
 use generate_config::generate_config;
+use login::{autologin::MOBILE_TOKENS_FILE, session::SESSION_CACHE_FILE};
 
 #[tokio::main]
 async fn main() -> crate::Result<()> {
@@ -38,14 +47,19 @@ async fn main() -> crate::Result<()> {
     if cli.no_animation {
         tracing_subscriber::registry()
             .with(reload_layer)
-            .with(tracing_subscriber::fmt::layer().with_ansi(false).compact())
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(RedactingMakeWriter(std::io::stdout))
+                    .with_ansi(false)
+                    .compact(),
+            )
             .init();
     } else {
         tracing_subscriber::registry()
             .with(reload_layer)
             .with(
                 tracing_subscriber::fmt::layer()
-                    .with_writer(indicatif_layer.get_stderr_writer())
+                    .with_writer(RedactingMakeWriter(indicatif_layer.get_stderr_writer()))
                     .compact(),
             )
             .with(indicatif_layer)
@@ -54,7 +68,24 @@ async fn main() -> crate::Result<()> {
 
     match cli.command {
         cli::Command::Sync { config_path } => {
-            let config = Arc::new(read_config(&config_path)?);
+            let config = read_config(&config_path)?;
+
+            // Get download path
+            let download_path = match &config.dir {
+                // We can safely unwrap, as the config can't be at /
+                Some(path) => config_path.parent().unwrap().join(path),
+                None => config_path.parent().unwrap().to_path_buf(),
+            };
+
+            // Every instance logs in on its own, everything else is shared
+            let mut instances = Vec::new();
+            for (mut instance_config, path) in config.instance_configs(&download_path) {
+                instance_config.session_cache = Some(path.join(SESSION_CACHE_FILE));
+                instance_config.mobile_tokens = Some(path.join(MOBILE_TOKENS_FILE));
+                instance_config.renew_invalid_wstoken(&config_path).await?;
+                instances.push((Arc::new(instance_config), path));
+            }
+            let config = Arc::new(config);
 
             let shutdown_config = config.clone();
             tokio::spawn(async move {
@@ -71,25 +102,26 @@ async fn main() -> crate::Result<()> {
             });
 
             // Start Login
-            let login_handle = Config::login_thread(config.clone()).await;
+            let mut login_handles = Vec::new();
+            for (instance_config, _) in &instances {
+                login_handles.push(Config::login_thread(instance_config.clone()).await);
+            }
             // Spawn youtube downloader threads
             let youtube_handle = Config::create_youtube_download_threads(config.clone()).await;
 
-            // Get download path
-            let download_path = match &config.dir {
-                // We can safely unwrap, as the config can't be at /
-                Some(path) => &config_path.parent().unwrap().join(path),
-                None => config_path.parent().unwrap(),
-            };
-
             // Start sync
-            Config::download_courses(config.clone(), &download_path).await;
+            join_all(instances.iter().map(|(instance_config, path)| {
+                Config::download_courses(instance_config.clone(), path)
+            }))
+            .await;
 
             // Allow youtube downloader threads to stop gracefully
             config.youtube_queue.sender.close();
             youtube_handle.wait_for_completion().await;
             // Start chromium shutdown
-            config.chromium_close().await;
+            for (instance_config, _) in &instances {
+                instance_config.chromium_close().await;
+            }
 
             // Stop outputting more messages
             reload_handle
@@ -100,13 +132,28 @@ async fn main() -> crate::Result<()> {
             config.write_log_to_file(false).await?;
 
             // Wait till chromium is stopped gracefully
-            config.chromium_wait().await;
+            for (instance_config, _) in &instances {
+                instance_config.chromium_wait().await;
+            }
             // Kill tasks that are no longer needed.
-            login_handle.abort();
+            for login_handle in login_handles {
+                login_handle.abort();
+            }
+        }
+        cli::Command::Setup { args } => {
+            generate_config(*args).await?;
+        }
+        cli::Command::Deadlines { config_path, days } => {
+            list_deadlines(&config_path, days).await?;
         }
-        cli::Command::Setup {} => {
-            generate_config().await?;
+        cli::Command::Courses { command } => {
+            manage_courses(command).await?;
         }
+        cli::Command::Config { command } => match command {
+            cli::ConfigCommand::Check { config_path } => {
+                check_config(&config_path)?;
+            }
+        },
     }
 
     Ok(())
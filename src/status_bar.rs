@@ -15,6 +15,7 @@ pub struct StatusBar {
     skipped: Mutex<usize>,
     updated: Mutex<usize>,
     new: Mutex<usize>,
+    graded: Mutex<usize>,
     err: Mutex<usize>,
     log: Mutex<Vec<String>>,
 }
@@ -25,11 +26,12 @@ impl StatusBar {
         let skipped = self.skipped.lock().await;
         let updated = self.updated.lock().await;
         let new = self.new.lock().await;
+        let graded = self.graded.lock().await;
         let err = self.err.lock().await;
 
         format!(
-            "Unchanged \x1b[90m{}\x1b[0m / Skipped \x1b[33m{}\x1b[0m / Updated \x1b[34m{}\x1b[0m / New \x1b[32m{}\x1b[0m / Err \x1b[31m{}\x1b[0m",
-            unchanged, skipped, updated, new, err
+            "Unchanged \x1b[90m{}\x1b[0m / Skipped \x1b[33m{}\x1b[0m / Updated \x1b[34m{}\x1b[0m / New \x1b[32m{}\x1b[0m / Graded \x1b[35m{}\x1b[0m / Err \x1b[31m{}\x1b[0m",
+            unchanged, skipped, updated, new, graded, err
         )
     }
 
@@ -60,6 +62,13 @@ impl StatusBar {
         *skipped += 1;
     }
 
+    pub async fn register_skipped_reason(&self, message: &str) {
+        let mut skipped = self.skipped.lock().await;
+        *skipped += 1;
+        let entry = self.create_log_entry(message, "Skipped: ").await;
+        info!("{}", entry);
+    }
+
     pub async fn register_updated<'a>(&self, message: impl Into<&'a str>) {
         let mut updated = self.updated.lock().await;
         *updated += 1;
@@ -74,6 +83,13 @@ impl StatusBar {
         info!("{}", entry);
     }
 
+    pub async fn register_graded(&self, message: &str) {
+        let mut graded = self.graded.lock().await;
+        *graded += 1;
+        let entry = self.create_log_entry(message, "Graded: ").await;
+        info!("{}", entry);
+    }
+
     pub async fn register_err(&self, message: &str) {
         let mut err = self.err.lock().await;
         *err += 1;
@@ -6,38 +6,40 @@ use futures::future::join_all;
 use tracing::{Span, instrument};
 use tracing_indicatif::span_ext::IndicatifSpanExt;
 
-use crate::{api::Download, config::sync_config::Course};
+use crate::{
+    api::{Download, deduplicate_names},
+    config::sync_config::Course,
+    download::sanitize_path_component,
+};
 
 use super::*;
 
 impl Config {
     async fn download_course(config: Arc<Config>, path: &Path, course: &Course) -> Result<()> {
-        let path = path.join(&course.name);
-
-        let grade_config = config.clone();
-        let grade_path = path.clone();
-        let grade_course_id = course.id;
-        let grade_handle = tokio::spawn(async move {
-            grade_config
-                .save_grades_table(&grade_path, grade_course_id)
-                .await;
-        });
+        let config = Config::course_config(&config, course);
+        let path = path.join(sanitize_path_component(&course.name));
 
         let context = format!("Failed getting course elements! Course: {}", &course.name);
 
-        let course_elements = config
+        let mut course_elements = config
             .api_core_course_get_contents(course.id)
             .await
             .context(context)?;
+        deduplicate_names(&mut course_elements, &config.path_template);
 
         // Create a task for each content
         let tasks = course_elements
             .iter()
-            .map(|r| r.download(config.clone(), &path));
-        let res = join_all(tasks).await;
+            .map(|r| r.download(config.clone(), &config.filter_scope, &path));
+        let summary = Config::save_course_summary(config.clone(), &path, course.id);
+        let (res, summary_res) = tokio::join!(join_all(tasks), summary);
 
-        // Wait for grades to be saved
-        let _ = grade_handle.await;
+        // The course contents are fine, even if the summary failed (e.g. missing permission)
+        if let Err(e) = summary_res {
+            config
+                .register_api_err(e.context(format!("Failed saving course summary: {}", course.id)))
+                .await;
+        }
 
         // Return an error if one occured
         for res in res {
@@ -61,11 +63,34 @@ impl Config {
             .courses
             .iter()
             .map(|course| Config::download_course(config.clone(), path, course));
+        // Grades are saved alongside the course contents
+        let grade_tasks = config.courses.iter().map(|course| {
+            let config = Config::course_config(&config, course);
+            let path = path.join(sanitize_path_component(&course.name));
+            async move { config.save_grades_table(&path, course.id).await }
+        });
+        let (res, grades, _) = tokio::join!(
+            join_all(tasks),
+            join_all(grade_tasks),
+            config.save_deadlines(path)
+        );
+
         // Print that an error occured in specific course
-        for res in join_all(tasks).await {
+        for res in res {
             if let Err(e) = res {
-                config.status_bar.register_err(&e.to_string()).await;
+                config.register_api_err(e).await;
             }
         }
+
+        // Combine the grades of all courses
+        let grades: Vec<_> = config
+            .courses
+            .iter()
+            .zip(grades)
+            .filter_map(|(course, grades)| Some((course.name.as_str(), grades?)))
+            .collect();
+        if !grades.is_empty() {
+            config.save_grades_overview(path, &grades).await;
+        }
     }
 }